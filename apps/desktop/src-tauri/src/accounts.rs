@@ -0,0 +1,143 @@
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::models::{Account, AccountInput};
+
+const ACCOUNT_TYPES: &[&str] = &["eval", "funded", "personal", "sim"];
+
+const ACCOUNT_COLUMNS: &str =
+    "id, name, broker, account_type, starting_balance, currency, created_at_utc, updated_at_utc";
+
+fn account_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Account> {
+    Ok(Account {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        broker: row.get(2)?,
+        account_type: row.get(3)?,
+        starting_balance: row.get(4)?,
+        currency: row.get(5)?,
+        created_at_utc: row.get(6)?,
+        updated_at_utc: row.get(7)?,
+    })
+}
+
+pub fn list_accounts(conn: &Connection) -> anyhow::Result<Vec<Account>> {
+    let mut stmt = conn.prepare(&format!("SELECT {ACCOUNT_COLUMNS} FROM accounts ORDER BY name ASC"))?;
+    let rows = stmt.query_map([], account_from_row)?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+pub fn get_account(conn: &Connection, id: &str) -> anyhow::Result<Account> {
+    conn.query_row(
+        &format!("SELECT {ACCOUNT_COLUMNS} FROM accounts WHERE id = ?1"),
+        params![id],
+        account_from_row,
+    )
+    .optional()?
+    .with_context(|| format!("unknown account: {id}"))
+}
+
+pub fn create_account(conn: &Connection, input: AccountInput) -> anyhow::Result<Account> {
+    validate_account(&input)?;
+
+    let id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT INTO accounts (id, name, broker, account_type, starting_balance, currency, created_at_utc, updated_at_utc)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            input.name.trim(),
+            input.broker.trim(),
+            input.account_type,
+            input.starting_balance,
+            input.currency.trim().to_uppercase(),
+            now,
+            now
+        ],
+    )?;
+
+    get_account(conn, &id)
+}
+
+pub fn update_account(conn: &Connection, id: &str, input: AccountInput) -> anyhow::Result<Account> {
+    validate_account(&input)?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let n = conn.execute(
+        "UPDATE accounts SET name=?2, broker=?3, account_type=?4, starting_balance=?5, currency=?6, updated_at_utc=?7
+         WHERE id=?1",
+        params![
+            id,
+            input.name.trim(),
+            input.broker.trim(),
+            input.account_type,
+            input.starting_balance,
+            input.currency.trim().to_uppercase(),
+            now
+        ],
+    )?;
+    if n == 0 {
+        anyhow::bail!("unknown account: {id}");
+    }
+
+    get_account(conn, id)
+}
+
+/// Trades of a deleted account are kept and become unassigned.
+pub fn delete_account(conn: &Connection, id: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM accounts WHERE id=?1", params![id])?;
+    Ok(())
+}
+
+/// Map a broker account label from an import ("Sim101", "APEX-4471-03") onto an
+/// account, creating it on first sight. Empty labels stay unassigned.
+pub fn account_for_label(conn: &Connection, label: &str, broker: &str) -> anyhow::Result<Option<String>> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Ok(None);
+    }
+
+    let existing: Option<String> = conn
+        .query_row("SELECT id FROM accounts WHERE name = ?1", params![label], |r| r.get(0))
+        .optional()?;
+    if existing.is_some() {
+        return Ok(existing);
+    }
+
+    // NinjaTrader and most platforms name simulation accounts "Sim...".
+    let account_type = if label.to_ascii_lowercase().starts_with("sim") { "sim" } else { "personal" };
+    let account = create_account(
+        conn,
+        AccountInput {
+            name: label.to_string(),
+            broker: broker.to_string(),
+            account_type: account_type.to_string(),
+            starting_balance: 0.0,
+            currency: "USD".to_string(),
+        },
+    )?;
+    Ok(Some(account.id))
+}
+
+fn validate_account(input: &AccountInput) -> anyhow::Result<()> {
+    if input.name.trim().is_empty() {
+        anyhow::bail!("account name is required");
+    }
+    if !ACCOUNT_TYPES.contains(&input.account_type.as_str()) {
+        anyhow::bail!("account type must be one of {}", ACCOUNT_TYPES.join(", "));
+    }
+    if !input.starting_balance.is_finite() || input.starting_balance < 0.0 {
+        anyhow::bail!("starting balance must be >= 0");
+    }
+    if input.currency.trim().len() != 3 {
+        anyhow::bail!("currency must be a 3-letter code");
+    }
+    Ok(())
+}
@@ -0,0 +1,419 @@
+use chrono::{Datelike, TimeZone, Timelike, Utc};
+use rusqlite::{params_from_iter, Connection};
+use std::collections::BTreeMap;
+
+use crate::journal::{local_date_str, parse_tz};
+use crate::models::{
+    AdherencePeriod, BreakdownBucket, DailyEquity, EquityCurve, EquityPoint, RuleAdherence, TagPerformance,
+    TradeFilter, TradeStats,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakdownBy {
+    /// Local entry time, in buckets of `bucket_minutes`.
+    TimeOfDay,
+    /// Local entry weekday, Monday first.
+    Weekday,
+    /// The trade's `session` label.
+    Session,
+    /// Time from entry to exit.
+    HoldDuration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdherenceInterval {
+    /// ISO weeks, "2024-W07".
+    Week,
+    /// Calendar months, "2024-02".
+    Month,
+}
+
+// Upper bound (exclusive, seconds) and label of each hold-duration bucket.
+const HOLD_BUCKETS: &[(i64, &str)] = &[
+    (60, "< 1m"),
+    (5 * 60, "1-5m"),
+    (15 * 60, "5-15m"),
+    (60 * 60, "15m-1h"),
+    (4 * 60 * 60, "1-4h"),
+    (24 * 60 * 60, "4h-1d"),
+    (i64::MAX, "> 1d"),
+];
+
+/// Performance stats over every trade matching `filter`, in exit-time order.
+///
+/// PnL is net of fees. Breakeven trades count toward the trade total but are
+/// neither wins nor losses.
+pub fn trade_stats(conn: &Connection, tz_name: &str, filter: &TradeFilter) -> anyhow::Result<TradeStats> {
+    let tz = parse_tz(tz_name)?;
+    let (where_sql, values) = crate::trade_filter::filter_sql(tz_name, filter)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT pnl_net, qty, fees, exit_time_utc FROM trades
+         WHERE {where_sql}
+         ORDER BY exit_time_utc ASC, id ASC"
+    ))?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, f64>(0)?,
+            row.get::<_, f64>(1)?,
+            row.get::<_, f64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+
+    let mut s = TradeStats::default();
+    let (mut win_streak, mut loss_streak) = (0, 0);
+    let (mut equity, mut peak) = (0.0_f64, 0.0_f64);
+    let mut peak_time: Option<i64> = None;
+
+    for r in rows {
+        let (pnl, qty, fees, exit_time_utc) = r?;
+        s.trade_count += 1;
+        s.net_pnl += pnl;
+        s.total_fees += fees;
+        s.total_contracts += qty;
+
+        if pnl > 0.0 {
+            s.wins += 1;
+            s.gross_profit += pnl;
+            s.largest_win = s.largest_win.max(pnl);
+            win_streak += 1;
+            loss_streak = 0;
+        } else if pnl < 0.0 {
+            s.losses += 1;
+            s.gross_loss += -pnl;
+            s.largest_loss = s.largest_loss.min(pnl);
+            loss_streak += 1;
+            win_streak = 0;
+        } else {
+            s.breakeven += 1;
+            win_streak = 0;
+            loss_streak = 0;
+        }
+        s.max_consecutive_wins = s.max_consecutive_wins.max(win_streak);
+        s.max_consecutive_losses = s.max_consecutive_losses.max(loss_streak);
+
+        // Drawdown on the closed-trade equity curve, starting from zero.
+        equity += pnl;
+        if equity > peak {
+            peak = equity;
+            peak_time = Some(exit_time_utc);
+        } else if peak - equity > s.max_drawdown {
+            s.max_drawdown = peak - equity;
+            s.max_drawdown_peak_date = peak_time.map(|t| local_date_str(tz, t));
+            s.max_drawdown_trough_date = Some(local_date_str(tz, exit_time_utc));
+        }
+    }
+
+    if s.trade_count > 0 {
+        s.win_rate_pct = s.wins as f64 / s.trade_count as f64 * 100.0;
+        s.expectancy = s.net_pnl / s.trade_count as f64;
+    }
+    if s.total_contracts > 0.0 {
+        s.expectancy_per_contract = s.net_pnl / s.total_contracts;
+    }
+    if s.wins > 0 {
+        s.avg_win = s.gross_profit / s.wins as f64;
+    }
+    if s.losses > 0 {
+        s.avg_loss = -s.gross_loss / s.losses as f64;
+    }
+    s.profit_factor = (s.gross_loss > 0.0).then(|| s.gross_profit / s.gross_loss);
+
+    Ok(s)
+}
+
+/// Cumulative net PnL over the trades matching `filter`, per trade and per
+/// local day. Days are bucketed like the journal calendar.
+pub fn equity_curve(conn: &Connection, tz_name: &str, filter: &TradeFilter) -> anyhow::Result<EquityCurve> {
+    let tz = parse_tz(tz_name)?;
+    let (where_sql, values) = crate::trade_filter::filter_sql(tz_name, filter)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, exit_time_utc, pnl_net FROM trades
+         WHERE {where_sql}
+         ORDER BY exit_time_utc ASC, id ASC"
+    ))?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, f64>(2)?))
+    })?;
+
+    let mut trades: Vec<EquityPoint> = Vec::new();
+    let mut days: Vec<DailyEquity> = Vec::new();
+    let (mut cumulative, mut hwm) = (0.0_f64, 0.0_f64);
+    for r in rows {
+        let (trade_id, exit_time_utc, pnl_net) = r?;
+        let date_local = local_date_str(tz, exit_time_utc);
+        cumulative += pnl_net;
+        hwm = hwm.max(cumulative);
+
+        match days.last_mut() {
+            Some(day) if day.date_local == date_local => {
+                day.trade_count += 1;
+                day.pnl_net += pnl_net;
+            }
+            _ => days.push(DailyEquity {
+                date_local: date_local.clone(),
+                trade_count: 1,
+                pnl_net,
+                cumulative: 0.0,
+                high_water_mark: 0.0,
+                drawdown: 0.0,
+            }),
+        }
+
+        trades.push(EquityPoint {
+            trade_id,
+            exit_time_utc,
+            date_local,
+            pnl_net,
+            cumulative,
+            high_water_mark: hwm,
+            drawdown: hwm - cumulative,
+        });
+    }
+
+    // Daily points are measured at each day's close.
+    let (mut cumulative, mut hwm) = (0.0_f64, 0.0_f64);
+    for day in &mut days {
+        cumulative += day.pnl_net;
+        hwm = hwm.max(cumulative);
+        day.cumulative = cumulative;
+        day.high_water_mark = hwm;
+        day.drawdown = hwm - cumulative;
+    }
+
+    Ok(EquityCurve { trades, days })
+}
+
+/// Count, net PnL, win rate and expectancy of the trades matching `filter`,
+/// grouped by `by`. Times are local to `tz_name`; empty buckets are omitted.
+pub fn breakdown(
+    conn: &Connection,
+    tz_name: &str,
+    filter: &TradeFilter,
+    by: BreakdownBy,
+    bucket_minutes: i64,
+) -> anyhow::Result<Vec<BreakdownBucket>> {
+    let tz = parse_tz(tz_name)?;
+    if by == BreakdownBy::TimeOfDay && !(1..=24 * 60).contains(&bucket_minutes) {
+        anyhow::bail!("bucket minutes must be between 1 and 1440");
+    }
+    let (where_sql, values) = crate::trade_filter::filter_sql(tz_name, filter)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT entry_time_utc, exit_time_utc, session, pnl_net FROM trades
+         WHERE {where_sql}"
+    ))?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, f64>(3)?,
+        ))
+    })?;
+
+    // Sort position -> bucket; sessions sort by name.
+    let mut buckets: BTreeMap<(i64, String), BreakdownBucket> = BTreeMap::new();
+    for r in rows {
+        let (entry_time_utc, exit_time_utc, session, pnl_net) = r?;
+        let entry_local = Utc
+            .timestamp_millis_opt(entry_time_utc)
+            .single()
+            .unwrap_or_default()
+            .with_timezone(&tz);
+
+        let (order, key) = match by {
+            BreakdownBy::TimeOfDay => {
+                let minute = i64::from(entry_local.hour() * 60 + entry_local.minute());
+                let start = minute / bucket_minutes * bucket_minutes;
+                (start, format!("{:02}:{:02}", start / 60, start % 60))
+            }
+            BreakdownBy::Weekday => {
+                let wd = entry_local.weekday();
+                (i64::from(wd.num_days_from_monday()), wd.to_string())
+            }
+            BreakdownBy::Session => (0, session),
+            BreakdownBy::HoldDuration => {
+                let secs = (exit_time_utc - entry_time_utc).max(0) / 1000;
+                let i = HOLD_BUCKETS.iter().position(|(upper, _)| secs < *upper).unwrap_or(HOLD_BUCKETS.len() - 1);
+                (i as i64, HOLD_BUCKETS[i].1.to_string())
+            }
+        };
+
+        let bucket = buckets.entry((order, key.clone())).or_insert_with(|| empty_bucket(&key));
+        add_to_bucket(bucket, pnl_net);
+    }
+
+    Ok(buckets.into_values().collect())
+}
+
+fn empty_bucket(key: &str) -> BreakdownBucket {
+    BreakdownBucket {
+        key: key.to_string(),
+        trade_count: 0,
+        wins: 0,
+        net_pnl: 0.0,
+        win_rate_pct: 0.0,
+        expectancy: 0.0,
+    }
+}
+
+fn add_to_bucket(b: &mut BreakdownBucket, pnl_net: f64) {
+    b.trade_count += 1;
+    b.net_pnl += pnl_net;
+    if pnl_net > 0.0 {
+        b.wins += 1;
+    }
+    b.win_rate_pct = b.wins as f64 / b.trade_count as f64 * 100.0;
+    b.expectancy = b.net_pnl / b.trade_count as f64;
+}
+
+/// Followed vs. broken results for every checklist rule over the trades
+/// matching `filter`. Trades created before a rule existed have no record for
+/// it and are left out of that rule's numbers.
+pub fn rule_adherence(
+    conn: &Connection,
+    tz_name: &str,
+    filter: &TradeFilter,
+    interval: AdherenceInterval,
+) -> anyhow::Result<Vec<RuleAdherence>> {
+    let tz = parse_tz(tz_name)?;
+    let (where_sql, values) = crate::trade_filter::filter_sql(tz_name, filter)?;
+
+    let mut out: Vec<RuleAdherence> = Vec::new();
+    let mut stmt = conn.prepare("SELECT id, label FROM rules ORDER BY sort_order ASC")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for r in rows {
+        let (rule_id, label) = r?;
+        out.push(RuleAdherence {
+            rule_id,
+            label,
+            adherence_pct: 0.0,
+            followed: empty_bucket("followed"),
+            broken: empty_bucket("broken"),
+            cost_of_breaking: None,
+            periods: Vec::new(),
+        });
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tr.rule_id, tr.checked, pnl_net, exit_time_utc
+         FROM trade_rules tr
+         JOIN trades ON trades.id = tr.trade_id
+         WHERE {where_sql}
+         ORDER BY exit_time_utc ASC"
+    ))?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)? != 0,
+            row.get::<_, f64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+
+    let format = match interval {
+        AdherenceInterval::Week => "%G-W%V",
+        AdherenceInterval::Month => "%Y-%m",
+    };
+    for r in rows {
+        let (rule_id, checked, pnl_net, exit_time_utc) = r?;
+        let Some(rule) = out.iter_mut().find(|a| a.rule_id == rule_id) else {
+            continue;
+        };
+        add_to_bucket(if checked { &mut rule.followed } else { &mut rule.broken }, pnl_net);
+
+        let period = Utc
+            .timestamp_millis_opt(exit_time_utc)
+            .single()
+            .unwrap_or_default()
+            .with_timezone(&tz)
+            .format(format)
+            .to_string();
+        if rule.periods.last().is_none_or(|p| p.period != period) {
+            rule.periods.push(AdherencePeriod {
+                period,
+                trade_count: 0,
+                followed: 0,
+                adherence_pct: 0.0,
+            });
+        }
+        let p = rule.periods.last_mut().expect("period pushed above");
+        p.trade_count += 1;
+        if checked {
+            p.followed += 1;
+        }
+        p.adherence_pct = p.followed as f64 / p.trade_count as f64 * 100.0;
+    }
+
+    for rule in &mut out {
+        let total = rule.followed.trade_count + rule.broken.trade_count;
+        if total > 0 {
+            rule.adherence_pct = rule.followed.trade_count as f64 / total as f64 * 100.0;
+        }
+        if rule.followed.trade_count > 0 && rule.broken.trade_count > 0 {
+            let gap = rule.followed.expectancy - rule.broken.expectancy;
+            rule.cost_of_breaking = Some(gap * rule.broken.trade_count as f64);
+        }
+    }
+    Ok(out)
+}
+
+/// Results per tag over the trades matching `filter`. A trade with several
+/// tags counts toward each of them; tags without trades are included.
+pub fn tag_performance(conn: &Connection, tz_name: &str, filter: &TradeFilter) -> anyhow::Result<Vec<TagPerformance>> {
+    let (where_sql, values) = crate::trade_filter::filter_sql(tz_name, filter)?;
+
+    let mut out: Vec<TagPerformance> = Vec::new();
+    for tag in crate::tags::list_tags(conn)? {
+        out.push(TagPerformance {
+            tag,
+            trade_count: 0,
+            wins: 0,
+            net_pnl: 0.0,
+            win_rate_pct: 0.0,
+            expectancy: 0.0,
+            profit_factor: None,
+        });
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tt.tag_id,
+                COUNT(*),
+                SUM(pnl_net > 0),
+                SUM(pnl_net),
+                SUM(CASE WHEN pnl_net > 0 THEN pnl_net ELSE 0 END),
+                SUM(CASE WHEN pnl_net < 0 THEN -pnl_net ELSE 0 END)
+         FROM trade_tags tt
+         JOIN trades ON trades.id = tt.trade_id
+         WHERE {where_sql}
+         GROUP BY tt.tag_id"
+    ))?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, f64>(3)?,
+            row.get::<_, f64>(4)?,
+            row.get::<_, f64>(5)?,
+        ))
+    })?;
+    for r in rows {
+        let (tag_id, count, wins, net, gross_profit, gross_loss) = r?;
+        let Some(p) = out.iter_mut().find(|p| p.tag.id == tag_id) else {
+            continue;
+        };
+        p.trade_count = count;
+        p.wins = wins;
+        p.net_pnl = net;
+        p.win_rate_pct = wins as f64 / count as f64 * 100.0;
+        p.expectancy = net / count as f64;
+        p.profit_factor = (gross_loss > 0.0).then(|| gross_profit / gross_loss);
+    }
+    Ok(out)
+}
@@ -0,0 +1,155 @@
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::ImageFormat;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::io::Cursor;
+use uuid::Uuid;
+
+use crate::models::{Attachment, AttachmentData, AttachmentInput};
+
+const MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+// A small file can still declare huge dimensions; cap what decoding may allocate.
+const MAX_IMAGE_SIDE: u32 = 10_000;
+const MAX_DECODE_BYTES: u64 = 512 * 1024 * 1024;
+const THUMBNAIL_SIZE: u32 = 320;
+
+const ATTACHMENT_COLUMNS: &str = "id, trade_id, journal_entry_id, file_name, mime_type, width, height, size_bytes,
+    thumbnail, created_at_utc";
+
+fn attachment_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        trade_id: row.get(1)?,
+        journal_entry_id: row.get(2)?,
+        file_name: row.get(3)?,
+        mime_type: row.get(4)?,
+        width: row.get(5)?,
+        height: row.get(6)?,
+        size_bytes: row.get(7)?,
+        thumbnail_base64: BASE64.encode(row.get::<_, Vec<u8>>(8)?),
+        created_at_utc: row.get(9)?,
+    })
+}
+
+/// Store a PNG, JPEG or WebP image on a trade or journal day. The original
+/// bytes are kept as-is; a PNG thumbnail is generated for listings.
+pub fn add_attachment(conn: &Connection, input: AttachmentInput) -> anyhow::Result<Attachment> {
+    if input.trade_id.is_some() == input.date_local.is_some() {
+        anyhow::bail!("attach to either a trade or a journal day");
+    }
+
+    // Check the image before resolving the target, so a rejected upload never creates a journal day.
+    let data = BASE64.decode(input.data_base64.trim()).context("invalid base64 image data")?;
+    if data.len() > MAX_ATTACHMENT_BYTES {
+        anyhow::bail!("image is larger than {} MB", MAX_ATTACHMENT_BYTES / (1024 * 1024));
+    }
+    let format = image::guess_format(&data).context("unrecognized image format")?;
+    let mime_type = match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::WebP => "image/webp",
+        other => anyhow::bail!("unsupported image format: {other:?} (use PNG, JPEG or WebP)"),
+    };
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_IMAGE_SIDE);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+    let mut reader = image::ImageReader::with_format(Cursor::new(&data), format);
+    reader.limits(limits);
+    let img = reader.decode().context("could not decode image")?;
+
+    let mut thumbnail = Vec::new();
+    img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Png)
+        .context("could not create thumbnail")?;
+
+    let file_name = match input.file_name.trim() {
+        "" => format!("image.{}", format.extensions_str().first().copied().unwrap_or("img")),
+        name => name.to_string(),
+    };
+
+    // Creating the journal day and storing the image succeed or fail together.
+    let id = Uuid::new_v4().to_string();
+    crate::db::with_savepoint(conn, || {
+        let (trade_id, journal_entry_id) = match (input.trade_id.as_deref(), input.date_local.as_deref()) {
+            (Some(trade_id), None) => {
+                let n: i64 = conn.query_row("SELECT COUNT(*) FROM trades WHERE id = ?1", params![trade_id], |r| r.get(0))?;
+                if n == 0 {
+                    anyhow::bail!("trade not found");
+                }
+                (Some(trade_id.to_string()), None)
+            }
+            (None, Some(date_local)) => (None, Some(crate::journal_entries::ensure_daily_entry(conn, date_local)?)),
+            _ => unreachable!("target checked above"),
+        };
+
+        let now = chrono::Utc::now().timestamp_millis();
+        conn.execute(
+            "INSERT INTO attachments (
+                id, trade_id, journal_entry_id, file_name, mime_type, width, height, size_bytes, data, thumbnail,
+                created_at_utc
+            ) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11)",
+            params![
+                id,
+                trade_id,
+                journal_entry_id,
+                file_name,
+                mime_type,
+                img.width(),
+                img.height(),
+                data.len() as i64,
+                data,
+                thumbnail,
+                now
+            ],
+        )?;
+        Ok(())
+    })?;
+
+    get_attachment(conn, &id).map(|a| a.attachment)
+}
+
+/// Attachments of a trade or of a journal day, oldest first.
+pub fn list_attachments(
+    conn: &Connection,
+    trade_id: Option<&str>,
+    date_local: Option<&str>,
+) -> anyhow::Result<Vec<Attachment>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ATTACHMENT_COLUMNS}
+         FROM attachments
+         WHERE (?1 IS NOT NULL AND trade_id = ?1)
+            OR (?2 IS NOT NULL AND journal_entry_id IN (
+                SELECT id FROM journal_entries WHERE date_local = ?2 AND type = 'daily'
+            ))
+         ORDER BY created_at_utc ASC"
+    ))?;
+    let rows = stmt.query_map(params![trade_id, date_local], attachment_from_row)?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+pub fn get_attachment(conn: &Connection, id: &str) -> anyhow::Result<AttachmentData> {
+    conn.query_row(
+        &format!("SELECT {ATTACHMENT_COLUMNS}, data FROM attachments WHERE id = ?1"),
+        params![id],
+        |row| {
+            Ok(AttachmentData {
+                attachment: attachment_from_row(row)?,
+                data_base64: BASE64.encode(row.get::<_, Vec<u8>>(10)?),
+            })
+        },
+    )
+    .optional()?
+    .context("attachment not found")
+}
+
+pub fn delete_attachment(conn: &Connection, id: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM attachments WHERE id=?1", params![id])?;
+    Ok(())
+}
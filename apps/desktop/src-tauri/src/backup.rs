@@ -38,10 +38,17 @@ pub fn export_db(app: &tauri::AppHandle, state: &DbState, dest_path: &Path) -> a
         }
     }
 
+    // A database with a recovery key cannot be opened without its key file.
+    let key_path = crate::recovery::key_file_path(&cfg_path);
+    if key_path.exists() {
+        let dest_keys = PathBuf::from(format!("{}.keys.json", dest_path.to_string_lossy()));
+        std::fs::copy(&key_path, &dest_keys).with_context(|| format!("copy key file to {}", dest_keys.display()))?;
+    }
+
     // Re-open automatically if unencrypted.
     if !cfg.encrypted {
         state.configure(src.to_string_lossy().to_string(), false);
-        state.open_existing(&src, false, None)?;
+        state.open_existing(&src, false, None, &cfg.cipher)?;
     } else {
         state.configure(src.to_string_lossy().to_string(), true);
     }
@@ -79,10 +86,19 @@ pub fn import_db(app: &tauri::AppHandle, state: &DbState, src_path: &Path) -> an
         }
     }
 
+    // The backup's key file (if any) replaces ours; a stale one would shadow the passphrase.
+    let key_path = crate::recovery::key_file_path(&cfg_path);
+    let src_keys = PathBuf::from(format!("{}.keys.json", src_path.to_string_lossy()));
+    if src_keys.exists() {
+        std::fs::copy(&src_keys, &key_path).with_context(|| format!("copy key file from {}", src_keys.display()))?;
+    } else if key_path.exists() {
+        std::fs::remove_file(&key_path).with_context(|| format!("remove {}", key_path.display()))?;
+    }
+
     // Re-open automatically if unencrypted. Encrypted will require unlock.
     if !cfg.encrypted {
         state.configure(dest.to_string_lossy().to_string(), false);
-        state.open_existing(&dest, false, None)?;
+        state.open_existing(&dest, false, None, &cfg.cipher)?;
     } else {
         state.configure(dest.to_string_lossy().to_string(), true);
     }
@@ -1,9 +1,17 @@
-use crate::{config, db::DbState};
-use crate::models::{DaySummary, JournalEntry, Rule, Settings, Trade, TradeHighlight, TradeInput, TradeWithRules};
-use crate::csv_import::CsvImportResult;
+use crate::{config::{self, CipherParams}, db::DbState};
+use crate::models::{Account, AccountInput, Attachment, AttachmentData, AttachmentInput, BreakdownBucket, DaySummary, EquityCurve, Execution, ExecutionInput, ImportBatch, ImportProfile, ImportProfileInput, Instrument, JournalEntry, PropReport, PropRules, PropRulesInput, Rule, RuleAdherence, SearchHit, Settings, Tag, TagGroup, TagInput, TagPerformance, Trade, TradeFilter, TradeHighlight, TradeQueryResult, TradeStats, TradeInput, TradeWithRules};
+use crate::csv_import::{CsvImportResult, CsvPreviewRow, ImportMode};
+use crate::executions::{BuildTradesResult, StoredExecution};
+use crate::analytics::{AdherenceInterval, BreakdownBy};
+use crate::matcher::MatchMethod;
+use crate::trades::TradeSort;
 
 use std::path::PathBuf;
-use tauri::Manager;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+/// Emitted whenever the database is locked, by `db_lock` or after inactivity.
+pub const DB_LOCKED_EVENT: &str = "db-locked";
 
 #[derive(Debug, serde::Serialize)]
 pub struct AppStatus {
@@ -90,10 +98,281 @@ pub fn rules_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(),
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn instruments_list(state: tauri::State<'_, DbState>) -> Result<Vec<Instrument>, String> {
+    state
+        .with_conn(crate::instruments::list_instruments)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn instruments_upsert(state: tauri::State<'_, DbState>, req: Instrument) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::instruments::upsert_instrument(conn, &req))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn instruments_delete(state: tauri::State<'_, DbState>, root_symbol: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::instruments::delete_instrument(conn, &root_symbol))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn accounts_list(state: tauri::State<'_, DbState>) -> Result<Vec<Account>, String> {
+    state
+        .with_conn(crate::accounts::list_accounts)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn accounts_create(state: tauri::State<'_, DbState>, input: AccountInput) -> Result<Account, String> {
+    state
+        .with_conn(|conn| crate::accounts::create_account(conn, input))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AccountUpdateRequest {
+    pub id: String,
+    pub input: AccountInput,
+}
+
+#[tauri::command]
+pub fn accounts_update(state: tauri::State<'_, DbState>, req: AccountUpdateRequest) -> Result<Account, String> {
+    state
+        .with_conn(|conn| crate::accounts::update_account(conn, &req.id, req.input))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn accounts_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::accounts::delete_account(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn prop_rules_get(state: tauri::State<'_, DbState>, account_id: String) -> Result<Option<PropRules>, String> {
+    state
+        .with_conn(|conn| crate::prop_rules::get_prop_rules(conn, &account_id))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PropRulesSetRequest {
+    pub account_id: String,
+    pub input: PropRulesInput,
+}
+
+#[tauri::command]
+pub fn prop_rules_set(state: tauri::State<'_, DbState>, req: PropRulesSetRequest) -> Result<PropRules, String> {
+    state
+        .with_conn(|conn| crate::prop_rules::set_prop_rules(conn, &req.account_id, req.input))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn prop_rules_delete(state: tauri::State<'_, DbState>, account_id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::prop_rules::delete_prop_rules(conn, &account_id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn prop_report(state: tauri::State<'_, DbState>, account_id: String) -> Result<PropReport, String> {
+    state
+        .with_conn(|conn| crate::prop_rules::prop_report(conn, &account_id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn analytics_stats(state: tauri::State<'_, DbState>, filter: TradeFilter) -> Result<TradeStats, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            crate::analytics::trade_stats(conn, &tz, &filter)
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn analytics_equity_curve(state: tauri::State<'_, DbState>, filter: TradeFilter) -> Result<EquityCurve, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            crate::analytics::equity_curve(conn, &tz, &filter)
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AnalyticsBreakdownRequest {
+    #[serde(default)]
+    pub filter: TradeFilter,
+    pub by: BreakdownBy,
+    pub bucket_minutes: Option<i64>,
+}
+
+#[tauri::command]
+pub fn analytics_breakdown(
+    state: tauri::State<'_, DbState>,
+    req: AnalyticsBreakdownRequest,
+) -> Result<Vec<BreakdownBucket>, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            crate::analytics::breakdown(conn, &tz, &req.filter, req.by, req.bucket_minutes.unwrap_or(60))
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AnalyticsRuleAdherenceRequest {
+    #[serde(default)]
+    pub filter: TradeFilter,
+    pub interval: Option<AdherenceInterval>,
+}
+
+#[tauri::command]
+pub fn analytics_rule_adherence(
+    state: tauri::State<'_, DbState>,
+    req: AnalyticsRuleAdherenceRequest,
+) -> Result<Vec<RuleAdherence>, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            let interval = req.interval.unwrap_or(AdherenceInterval::Week);
+            crate::analytics::rule_adherence(conn, &tz, &req.filter, interval)
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tag_groups_list(state: tauri::State<'_, DbState>) -> Result<Vec<TagGroup>, String> {
+    state
+        .with_conn(crate::tags::list_tag_groups)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tag_groups_upsert(state: tauri::State<'_, DbState>, req: TagGroup) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::tags::upsert_tag_group(conn, &req))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tag_groups_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::tags::delete_tag_group(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tags_list(state: tauri::State<'_, DbState>) -> Result<Vec<Tag>, String> {
+    state
+        .with_conn(crate::tags::list_tags)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tags_create(state: tauri::State<'_, DbState>, input: TagInput) -> Result<Tag, String> {
+    state
+        .with_conn(|conn| crate::tags::create_tag(conn, input))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TagUpdateRequest {
+    pub id: String,
+    pub input: TagInput,
+}
+
+#[tauri::command]
+pub fn tags_update(state: tauri::State<'_, DbState>, req: TagUpdateRequest) -> Result<Tag, String> {
+    state
+        .with_conn(|conn| crate::tags::update_tag(conn, &req.id, req.input))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tags_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::tags::delete_tag(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn analytics_tag_performance(
+    state: tauri::State<'_, DbState>,
+    filter: TradeFilter,
+) -> Result<Vec<TagPerformance>, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            crate::analytics::tag_performance(conn, &tz, &filter)
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn attachments_add(state: tauri::State<'_, DbState>, input: AttachmentInput) -> Result<Attachment, String> {
+    state
+        .with_conn(|conn| crate::attachments::add_attachment(conn, input))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AttachmentsListRequest {
+    pub trade_id: Option<String>,
+    pub date_local: Option<String>,
+}
+
+#[tauri::command]
+pub fn attachments_list(state: tauri::State<'_, DbState>, req: AttachmentsListRequest) -> Result<Vec<Attachment>, String> {
+    state
+        .with_conn(|conn| crate::attachments::list_attachments(conn, req.trade_id.as_deref(), req.date_local.as_deref()))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn attachments_get(state: tauri::State<'_, DbState>, id: String) -> Result<AttachmentData, String> {
+    state
+        .with_conn(|conn| crate::attachments::get_attachment(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn attachments_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::attachments::delete_attachment(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    pub limit: Option<i64>,
+}
+
+#[tauri::command]
+pub fn search(state: tauri::State<'_, DbState>, req: SearchRequest) -> Result<Vec<SearchHit>, String> {
+    let limit = req.limit.unwrap_or(50).clamp(1, 500);
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            crate::search::search(conn, &tz, &req.query, limit)
+        })
+        .map_err(|e| e.to_string())
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct TradesListRequest {
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    pub account_id: Option<String>,
 }
 
 #[tauri::command]
@@ -101,7 +380,31 @@ pub fn trades_list(state: tauri::State<'_, DbState>, req: TradesListRequest) ->
     let limit = req.limit.unwrap_or(200);
     let offset = req.offset.unwrap_or(0);
     state
-        .with_conn(|conn| crate::trades::list_trades(conn, limit, offset))
+        .with_conn(|conn| crate::trades::list_trades(conn, limit, offset, req.account_id.as_deref()))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TradesQueryRequest {
+    #[serde(default)]
+    pub filter: TradeFilter,
+    #[serde(default)]
+    pub sort: TradeSort,
+    pub descending: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[tauri::command]
+pub fn trades_query(state: tauri::State<'_, DbState>, req: TradesQueryRequest) -> Result<TradeQueryResult, String> {
+    let limit = req.limit.unwrap_or(200).clamp(1, 10_000);
+    let offset = req.offset.unwrap_or(0).max(0);
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            let descending = req.descending.unwrap_or(true);
+            crate::trades::query_trades(conn, &tz, &req.filter, req.sort, descending, limit, offset)
+        })
         .map_err(|e| e.to_string())
 }
 
@@ -139,10 +442,65 @@ pub fn trades_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(),
         .map_err(|e| e.to_string())
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct ExecutionsListRequest {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub unmatched_only: Option<bool>,
+}
+
+#[tauri::command]
+pub fn executions_list(state: tauri::State<'_, DbState>, req: ExecutionsListRequest) -> Result<Vec<Execution>, String> {
+    let limit = req.limit.unwrap_or(200);
+    let offset = req.offset.unwrap_or(0);
+    let unmatched_only = req.unmatched_only.unwrap_or(false);
+    state
+        .with_conn(|conn| crate::executions::list_executions(conn, limit, offset, unmatched_only))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn executions_create(state: tauri::State<'_, DbState>, input: ExecutionInput) -> Result<String, String> {
+    state
+        .with_conn(|conn| {
+            match crate::executions::insert_execution(conn, &input)? {
+                StoredExecution::Inserted(id) | StoredExecution::Updated(id) => Ok(id),
+                StoredExecution::Duplicate => anyhow::bail!("execution {} was already imported", input.external_id),
+            }
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn executions_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::executions::delete_execution(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ExecutionsBuildTradesRequest {
+    pub method: MatchMethod,
+}
+
+#[tauri::command]
+pub fn executions_build_trades(
+    state: tauri::State<'_, DbState>,
+    req: ExecutionsBuildTradesRequest,
+) -> Result<BuildTradesResult, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            crate::executions::build_trades(conn, &tz, req.method, None)
+        })
+        .map_err(|e| e.to_string())
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct JournalMonthSummaryRequest {
     pub year: i32,
     pub month: u32, // 1-12
+    pub account_id: Option<String>,
 }
 
 #[tauri::command]
@@ -150,7 +508,7 @@ pub fn journal_month_summary(state: tauri::State<'_, DbState>, req: JournalMonth
     state
         .with_conn(|conn| {
             let tz = crate::settings::get_timezone(conn)?;
-            crate::journal::month_summary(conn, &tz, req.year, req.month)
+            crate::journal::month_summary(conn, &tz, req.year, req.month, req.account_id.as_deref())
         })
         .map_err(|e| e.to_string())
 }
@@ -158,6 +516,7 @@ pub fn journal_month_summary(state: tauri::State<'_, DbState>, req: JournalMonth
 #[derive(Debug, serde::Deserialize)]
 pub struct JournalDayTradesRequest {
     pub date_local: String, // YYYY-MM-DD
+    pub account_id: Option<String>,
 }
 
 #[tauri::command]
@@ -165,7 +524,7 @@ pub fn journal_day_trades(state: tauri::State<'_, DbState>, req: JournalDayTrade
     state
         .with_conn(|conn| {
             let tz = crate::settings::get_timezone(conn)?;
-            crate::journal::day_trades(conn, &tz, &req.date_local)
+            crate::journal::day_trades(conn, &tz, &req.date_local, req.account_id.as_deref())
         })
         .map_err(|e| e.to_string())
 }
@@ -225,6 +584,8 @@ pub fn journal_entry_upsert(state: tauri::State<'_, DbState>, req: JournalEntryU
 #[derive(Debug, serde::Deserialize)]
 pub struct CsvImportGenericRequest {
     pub path: String,
+    pub profile_id: Option<String>,
+    pub mode: Option<ImportMode>,
 }
 
 #[tauri::command]
@@ -233,7 +594,195 @@ pub fn csv_import_generic(state: tauri::State<'_, DbState>, req: CsvImportGeneri
         .with_conn(|conn| {
             let tz = crate::settings::get_timezone(conn)?;
             let path = std::path::PathBuf::from(req.path);
-            crate::csv_import::import_generic_csv(conn, &path, &tz)
+            crate::csv_import::import_generic_csv(conn, &path, &tz, req.profile_id.as_deref(), req.mode.unwrap_or_default())
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CsvImportPreviewRequest {
+    pub path: String,
+    pub profile_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[tauri::command]
+pub fn csv_import_preview(
+    state: tauri::State<'_, DbState>,
+    req: CsvImportPreviewRequest,
+) -> Result<Vec<CsvPreviewRow>, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            let path = std::path::PathBuf::from(req.path);
+            crate::csv_import::preview_generic_csv(conn, &path, &tz, req.profile_id.as_deref(), req.limit.unwrap_or(20))
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ImportsListRequest {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[tauri::command]
+pub fn imports_list(state: tauri::State<'_, DbState>, req: ImportsListRequest) -> Result<Vec<ImportBatch>, String> {
+    let limit = req.limit.unwrap_or(100);
+    let offset = req.offset.unwrap_or(0);
+    state
+        .with_conn(|conn| crate::imports::list_imports(conn, limit, offset))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn imports_undo(state: tauri::State<'_, DbState>, id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::imports::undo_import(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn import_profiles_list(state: tauri::State<'_, DbState>) -> Result<Vec<ImportProfile>, String> {
+    state
+        .with_conn(crate::import_profiles::list_profiles)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn import_profiles_create(state: tauri::State<'_, DbState>, input: ImportProfileInput) -> Result<ImportProfile, String> {
+    state
+        .with_conn(|conn| crate::import_profiles::create_profile(conn, input))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ImportProfileUpdateRequest {
+    pub id: String,
+    pub input: ImportProfileInput,
+}
+
+#[tauri::command]
+pub fn import_profiles_update(
+    state: tauri::State<'_, DbState>,
+    req: ImportProfileUpdateRequest,
+) -> Result<ImportProfile, String> {
+    state
+        .with_conn(|conn| crate::import_profiles::update_profile(conn, &req.id, req.input))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn import_profiles_delete(state: tauri::State<'_, DbState>, id: String) -> Result<(), String> {
+    state
+        .with_conn(|conn| crate::import_profiles::delete_profile(conn, &id))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CsvImportNinjaTraderRequest {
+    pub path: String,
+    pub day_first: Option<bool>,
+    pub method: Option<MatchMethod>,
+    pub mode: Option<ImportMode>,
+}
+
+#[tauri::command]
+pub fn csv_import_ninjatrader(
+    state: tauri::State<'_, DbState>,
+    req: CsvImportNinjaTraderRequest,
+) -> Result<CsvImportResult, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            let path = std::path::PathBuf::from(req.path);
+            let opts = crate::csv_ninjatrader::NinjaTraderOptions {
+                day_first: req.day_first.unwrap_or(false),
+                method: req.method.unwrap_or(MatchMethod::Fifo),
+                mode: req.mode.unwrap_or_default(),
+            };
+            crate::csv_ninjatrader::import_ninjatrader_csv(conn, &path, &tz, opts)
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CsvImportTradovateRequest {
+    pub path: String,
+    pub method: Option<MatchMethod>,
+    pub mode: Option<ImportMode>,
+}
+
+#[tauri::command]
+pub fn csv_import_tradovate(
+    state: tauri::State<'_, DbState>,
+    req: CsvImportTradovateRequest,
+) -> Result<CsvImportResult, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            let path = std::path::PathBuf::from(req.path);
+            crate::csv_tradovate::import_tradovate_csv(
+                conn,
+                &path,
+                &tz,
+                req.method.unwrap_or(MatchMethod::Fifo),
+                req.mode.unwrap_or_default(),
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CsvImportRithmicRequest {
+    pub path: String,
+    pub method: Option<MatchMethod>,
+    pub mode: Option<ImportMode>,
+}
+
+#[tauri::command]
+pub fn csv_import_rithmic(
+    state: tauri::State<'_, DbState>,
+    req: CsvImportRithmicRequest,
+) -> Result<CsvImportResult, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            let path = std::path::PathBuf::from(req.path);
+            crate::csv_rithmic::import_rithmic_csv(
+                conn,
+                &path,
+                &tz,
+                req.method.unwrap_or(MatchMethod::Fifo),
+                req.mode.unwrap_or_default(),
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct IbkrImportFlexRequest {
+    pub path: String,
+    pub method: Option<MatchMethod>,
+    pub mode: Option<ImportMode>,
+}
+
+#[tauri::command]
+pub fn ibkr_import_flex(
+    state: tauri::State<'_, DbState>,
+    req: IbkrImportFlexRequest,
+) -> Result<CsvImportResult, String> {
+    state
+        .with_conn(|conn| {
+            let tz = crate::settings::get_timezone(conn)?;
+            let path = std::path::PathBuf::from(req.path);
+            crate::ibkr_flex::import_ibkr_flex(
+                conn,
+                &path,
+                &tz,
+                req.method.unwrap_or(MatchMethod::Fifo),
+                req.mode.unwrap_or_default(),
+            )
         })
         .map_err(|e| e.to_string())
 }
@@ -246,6 +795,14 @@ fn config_path(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
     Ok(dir.join("ftjournal.json"))
 }
 
+/// Cipher settings to keep when re-keying: the configured ones, but never
+/// weaker than the defaults (an old config still names the 64k settings).
+fn keep_cipher(cfg_path: &PathBuf) -> anyhow::Result<CipherParams> {
+    let cfg = config::load_config(cfg_path)?.ok_or_else(|| anyhow::anyhow!("App not initialized"))?;
+    let default = CipherParams::default();
+    Ok(if cfg.cipher.weaker_than(&default) { default } else { cfg.cipher })
+}
+
 fn default_db_path(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
     let dir = app
         .path()
@@ -263,60 +820,243 @@ pub fn app_get_status(state: tauri::State<'_, DbState>) -> AppStatus {
 pub struct DbInitRequest {
     pub encrypted: bool,
     pub passphrase: Option<String>,
+    /// Also generate a recovery key that can unlock the database instead of the passphrase.
+    #[serde(default)]
+    pub recovery_key: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DbInitResult {
+    #[serde(flatten)]
+    pub status: AppStatus,
+    /// Shown to the user once; it is not stored anywhere in readable form.
+    pub recovery_key: Option<String>,
 }
 
 #[tauri::command]
-pub fn db_init(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbInitRequest) -> Result<AppStatus, String> {
+pub fn db_init(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbInitRequest) -> Result<DbInitResult, String> {
     let cfg_path = config_path(&app).map_err(|e| e.to_string())?;
+    let key_path = crate::recovery::key_file_path(&cfg_path);
     let db_path = default_db_path(&app).map_err(|e| e.to_string())?;
 
     state.configure(db_path.to_string_lossy().to_string(), req.encrypted);
 
-    state
-        .create_new(&db_path, req.encrypted, req.passphrase.as_deref())
-        .map_err(|e| e.to_string())?;
+    let cfg = config::AppConfig::default_at(db_path.clone(), req.encrypted);
+    let recovery_key = if req.encrypted && req.recovery_key {
+        let passphrase = req.passphrase.as_deref().unwrap_or_default();
+        Some(
+            crate::recovery::create_with_recovery_key(state.inner(), &db_path, &key_path, passphrase, &cfg.cipher)
+                .map_err(|e| e.to_string())?,
+        )
+    } else {
+        state
+            .create_new(&db_path, req.encrypted, req.passphrase.as_deref(), &cfg.cipher)
+            .map_err(|e| e.to_string())?;
+        // A key file left over from an earlier install would shadow the new passphrase.
+        let _ = std::fs::remove_file(&key_path);
+        None
+    };
 
-    let cfg = config::AppConfig::default_at(db_path, req.encrypted);
     config::save_config(&cfg_path, &cfg).map_err(|e| e.to_string())?;
+    state.set_auto_lock(cfg.auto_lock_minutes);
 
-    Ok(AppStatus { db: state.status() })
+    Ok(DbInitResult {
+        status: AppStatus { db: state.status() },
+        recovery_key,
+    })
 }
 
 #[derive(Debug, serde::Deserialize)]
 pub struct DbUnlockRequest {
+    /// The passphrase, or the recovery key if the database has one.
     pub passphrase: Option<String>,
 }
 
 #[tauri::command]
 pub fn db_unlock(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbUnlockRequest) -> Result<AppStatus, String> {
     let cfg_path = config_path(&app).map_err(|e| e.to_string())?;
+    crate::rekey::recover(&cfg_path).map_err(|e| e.to_string())?;
     let cfg = config::load_config(&cfg_path)
         .map_err(|e| e.to_string())?
         .ok_or_else(|| "App not initialized".to_string())?;
 
     let db_path = PathBuf::from(cfg.db_path.clone());
-    state.configure(cfg.db_path, cfg.encrypted);
+    state.configure(cfg.db_path.clone(), cfg.encrypted);
+    state.set_auto_lock(cfg.auto_lock_minutes);
 
-    state
-        .open_existing(&db_path, cfg.encrypted, req.passphrase.as_deref())
+    if !cfg.encrypted {
+        state
+            .open_existing(&db_path, false, req.passphrase.as_deref(), &cfg.cipher)
+            .map_err(|e| e.to_string())?;
+        return Ok(AppStatus { db: state.status() });
+    }
+
+    let passphrase = req.passphrase.as_deref().ok_or_else(|| "passphrase required for encrypted database".to_string())?;
+    let key = crate::recovery::resolve_db_key(&crate::recovery::key_file_path(&cfg_path), passphrase)
+        .map_err(|e| e.to_string())?;
+    let cipher = state.unlock(&db_path, Some(&key), &cfg.cipher).map_err(|e| e.to_string())?;
+    // The file on disk is authoritative (e.g. after restoring an older backup).
+    if cipher != cfg.cipher {
+        let cfg = config::AppConfig { cipher, ..cfg };
+        config::save_config(&cfg_path, &cfg).map_err(|e| e.to_string())?;
+    }
+
+    Ok(AppStatus { db: state.status() })
+}
+
+/// Close the encrypted database; `db_unlock` opens it again.
+#[tauri::command]
+pub fn db_lock(app: tauri::AppHandle, state: tauri::State<'_, DbState>) -> Result<AppStatus, String> {
+    if !*state.encrypted.lock() {
+        return Err("only an encrypted database can be locked".to_string());
+    }
+    state.close();
+    let _ = app.emit(DB_LOCKED_EVENT, state.status());
+    Ok(AppStatus { db: state.status() })
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DbSetAutoLockRequest {
+    /// `None` (or 0) turns auto-lock off.
+    pub minutes: Option<u32>,
+}
+
+#[tauri::command]
+pub fn db_set_auto_lock(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbSetAutoLockRequest) -> Result<AppStatus, String> {
+    let cfg_path = config_path(&app).map_err(|e| e.to_string())?;
+    let cfg = config::load_config(&cfg_path)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "App not initialized".to_string())?;
+
+    let cfg = config::AppConfig {
+        auto_lock_minutes: req.minutes.filter(|m| *m > 0),
+        ..cfg
+    };
+    config::save_config(&cfg_path, &cfg).map_err(|e| e.to_string())?;
+    state.set_auto_lock(cfg.auto_lock_minutes);
+    Ok(AppStatus { db: state.status() })
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DbChangePassphraseRequest {
+    pub current_passphrase: String,
+    pub new_passphrase: String,
+}
+
+#[tauri::command]
+pub fn db_change_passphrase(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbChangePassphraseRequest) -> Result<AppStatus, String> {
+    if req.new_passphrase.is_empty() {
+        return Err("new passphrase is required".to_string());
+    }
+    if !*state.encrypted.lock() {
+        return Err("database is not encrypted".to_string());
+    }
+    let cfg_path = config_path(&app).map_err(|e| e.to_string())?;
+
+    // With a recovery key only the passphrase's copy of the data key changes;
+    // `current_passphrase` may be the recovery key.
+    let key_path = crate::recovery::key_file_path(&cfg_path);
+    if let Some(mut file) = crate::recovery::load_key_file(&key_path).map_err(|e| e.to_string())? {
+        crate::recovery::rewrap_passphrase(&mut file, &req.current_passphrase, &req.new_passphrase)
+            .map_err(|e| e.to_string())?;
+        crate::recovery::save_key_file(&key_path, &file).map_err(|e| e.to_string())?;
+        return Ok(AppStatus { db: state.status() });
+    }
+
+    let cipher = keep_cipher(&cfg_path).map_err(|e| e.to_string())?;
+    crate::rekey::change_encryption(state.inner(), &cfg_path, Some(&req.current_passphrase), Some(&req.new_passphrase), &cipher)
+        .map_err(|e| e.to_string())?;
+    Ok(AppStatus { db: state.status() })
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DbEncryptRequest {
+    pub passphrase: String,
+}
+
+/// Move a plaintext database to Secure mode.
+#[tauri::command]
+pub fn db_encrypt(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbEncryptRequest) -> Result<AppStatus, String> {
+    if req.passphrase.is_empty() {
+        return Err("passphrase is required".to_string());
+    }
+    if *state.encrypted.lock() {
+        return Err("database is already encrypted".to_string());
+    }
+    let cfg_path = config_path(&app).map_err(|e| e.to_string())?;
+    let cipher = keep_cipher(&cfg_path).map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(crate::recovery::key_file_path(&cfg_path));
+    crate::rekey::change_encryption(state.inner(), &cfg_path, None, Some(&req.passphrase), &cipher)
+        .map_err(|e| e.to_string())?;
+    Ok(AppStatus { db: state.status() })
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DbDecryptRequest {
+    pub current_passphrase: String,
+}
+
+/// Turn Secure mode off, leaving a plaintext database.
+#[tauri::command]
+pub fn db_decrypt(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbDecryptRequest) -> Result<AppStatus, String> {
+    let cfg_path = config_path(&app).map_err(|e| e.to_string())?;
+    let key_path = crate::recovery::key_file_path(&cfg_path);
+    let key = crate::recovery::resolve_db_key(&key_path, &req.current_passphrase).map_err(|e| e.to_string())?;
+    let cipher = keep_cipher(&cfg_path).map_err(|e| e.to_string())?;
+    crate::rekey::change_encryption(state.inner(), &cfg_path, Some(&key), None, &cipher)
         .map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(&key_path);
+    Ok(AppStatus { db: state.status() })
+}
 
+#[derive(Debug, serde::Deserialize)]
+pub struct DbUpgradeCipherRequest {
+    pub passphrase: String,
+    /// Defaults to the settings new databases get.
+    pub cipher: Option<CipherParams>,
+}
+
+/// Re-encrypt the database with stronger (or the given) SQLCipher settings.
+#[tauri::command]
+pub fn db_upgrade_cipher(app: tauri::AppHandle, state: tauri::State<'_, DbState>, req: DbUpgradeCipherRequest) -> Result<AppStatus, String> {
+    if !*state.encrypted.lock() {
+        return Err("database is not encrypted".to_string());
+    }
+    let cfg_path = config_path(&app).map_err(|e| e.to_string())?;
+    let cipher = req.cipher.unwrap_or_default();
+    let key = crate::recovery::resolve_db_key(&crate::recovery::key_file_path(&cfg_path), &req.passphrase)
+        .map_err(|e| e.to_string())?;
+    crate::rekey::change_encryption(state.inner(), &cfg_path, Some(&key), Some(&key), &cipher)
+        .map_err(|e| e.to_string())?;
     Ok(AppStatus { db: state.status() })
 }
 
 pub fn try_autoload(app: &tauri::AppHandle, state: &DbState) -> anyhow::Result<()> {
     let cfg_path = config_path(app)?;
+    crate::rekey::recover(&cfg_path)?;
     let Some(cfg) = config::load_config(&cfg_path)? else {
         return Ok(());
     };
 
     state.configure(cfg.db_path.clone(), cfg.encrypted);
+    state.set_auto_lock(cfg.auto_lock_minutes);
 
     if !cfg.encrypted {
         let db_path = PathBuf::from(cfg.db_path);
         // Open unencrypted automatically.
-        state.open_existing(&db_path, false, None)?;
+        state.open_existing(&db_path, false, None, &cfg.cipher)?;
     }
 
     Ok(())
 }
+
+/// Background check that locks an idle encrypted database and tells the frontend.
+pub fn spawn_idle_lock(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_secs(30));
+        let state: tauri::State<'_, DbState> = app.state();
+        if state.lock_if_idle(Instant::now()) {
+            let _ = app.emit(DB_LOCKED_EVENT, state.status());
+        }
+    });
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::io::Write;
 use std::{fs, path::PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,6 +7,28 @@ pub struct AppConfig {
     pub db_path: String,
     pub encrypted: bool,
     pub schema_version: u32,
+    /// Configs written before this field existed belong to databases created
+    /// with the original 64k-iteration settings.
+    #[serde(default = "CipherParams::legacy")]
+    pub cipher: CipherParams,
+    /// Lock an encrypted database after this many idle minutes; `None` turns it off.
+    #[serde(default = "default_auto_lock_minutes")]
+    pub auto_lock_minutes: Option<u32>,
+    /// Set only while a re-encryption swaps files, so one cut short by a crash
+    /// can be finished or undone on the next start (see `rekey::recover`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_rekey: Option<PendingRekey>,
+}
+
+/// Settings the database will have once an interrupted re-encryption completes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingRekey {
+    pub encrypted: bool,
+    pub cipher: CipherParams,
+}
+
+fn default_auto_lock_minutes() -> Option<u32> {
+    Some(15)
 }
 
 impl AppConfig {
@@ -14,10 +37,73 @@ impl AppConfig {
             db_path: db_path.to_string_lossy().to_string(),
             encrypted,
             schema_version: 1,
+            cipher: CipherParams::default(),
+            auto_lock_minutes: default_auto_lock_minutes(),
+            pending_rekey: None,
+        }
+    }
+}
+
+/// SQLCipher settings an encrypted database was created with. They are part
+/// of the file format: opening with different values fails like a wrong key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub page_size: u32,
+    pub kdf_iter: u32,
+    pub hmac_algorithm: String,
+    pub kdf_algorithm: String,
+}
+
+const HMAC_ALGORITHMS: &[&str] = &["HMAC_SHA1", "HMAC_SHA256", "HMAC_SHA512"];
+const KDF_ALGORITHMS: &[&str] = &["PBKDF2_HMAC_SHA1", "PBKDF2_HMAC_SHA256", "PBKDF2_HMAC_SHA512"];
+
+/// Used for new databases and re-encryption (SQLCipher 4 defaults).
+impl Default for CipherParams {
+    fn default() -> Self {
+        Self {
+            page_size: 4096,
+            kdf_iter: 256_000,
+            hmac_algorithm: "HMAC_SHA512".to_string(),
+            kdf_algorithm: "PBKDF2_HMAC_SHA512".to_string(),
         }
     }
 }
 
+impl CipherParams {
+    /// Settings of databases created before they were configurable.
+    pub fn legacy() -> Self {
+        Self {
+            kdf_iter: 64_000,
+            ..Self::default()
+        }
+    }
+
+    /// Whether these settings resist brute force less than `other`: fewer KDF
+    /// iterations or a weaker hash. Page size does not affect strength.
+    pub fn weaker_than(&self, other: &CipherParams) -> bool {
+        let rank = |alg: &str| ["SHA1", "SHA256", "SHA512"].iter().position(|h| alg.ends_with(h));
+        self.kdf_iter < other.kdf_iter
+            || rank(&self.hmac_algorithm) < rank(&other.hmac_algorithm)
+            || rank(&self.kdf_algorithm) < rank(&other.kdf_algorithm)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.page_size.is_power_of_two() || !(512..=65536).contains(&self.page_size) {
+            anyhow::bail!("cipher page size must be a power of two between 512 and 65536");
+        }
+        if self.kdf_iter < 1000 {
+            anyhow::bail!("kdf_iter must be at least 1000");
+        }
+        if !HMAC_ALGORITHMS.contains(&self.hmac_algorithm.as_str()) {
+            anyhow::bail!("unsupported HMAC algorithm: {}", self.hmac_algorithm);
+        }
+        if !KDF_ALGORITHMS.contains(&self.kdf_algorithm.as_str()) {
+            anyhow::bail!("unsupported KDF algorithm: {}", self.kdf_algorithm);
+        }
+        Ok(())
+    }
+}
+
 pub fn load_config(config_path: &PathBuf) -> anyhow::Result<Option<AppConfig>> {
     if !config_path.exists() {
         return Ok(None);
@@ -32,6 +118,13 @@ pub fn save_config(config_path: &PathBuf, cfg: &AppConfig) -> anyhow::Result<()>
         fs::create_dir_all(parent)?;
     }
     let raw = serde_json::to_string_pretty(cfg)?;
-    fs::write(config_path, raw)?;
+
+    // Write beside the real file and rename over it, so a crash never leaves a half-written config.
+    let tmp_path = config_path.with_extension("json.tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(raw.as_bytes())?;
+    // Flushed before the rename, or a power loss can leave an empty file in its place.
+    file.sync_all()?;
+    fs::rename(&tmp_path, config_path)?;
     Ok(())
 }
@@ -4,7 +4,9 @@ use chrono_tz::Tz;
 use rusqlite::Connection;
 use std::path::Path;
 
-use crate::models::TradeInput;
+use crate::executions::StoredExecution;
+use crate::matcher::MatchMethod;
+use crate::models::{ExecutionInput, Instrument, TradeInput};
 
 #[derive(Debug, serde::Deserialize)]
 struct CsvRow {
@@ -20,8 +22,12 @@ struct CsvRow {
     exit_local: Option<String>,
 
     // Optional
+    account: Option<String>,
     market: Option<String>,
+    instrument: Option<String>,
     session: Option<String>,
+    entry_price: Option<f64>,
+    exit_price: Option<f64>,
     pnl_amount: Option<f64>,
     fees: Option<f64>,
     pnl_includes_fees: Option<bool>,
@@ -30,12 +36,29 @@ struct CsvRow {
 
 #[derive(Debug, serde::Serialize)]
 pub struct CsvImportResult {
+    pub import_id: Option<String>, // None when a strict import was rolled back
     pub created: i64,
     pub skipped: i64,
     pub errors: Vec<String>,
+    pub rolled_back: bool,
+    /// Roots this import added to the catalog with a made-up tick size, to be
+    /// corrected in the instrument settings.
+    pub placeholder_instruments: Vec<String>,
+    /// Earlier import of the identical file. When set, nothing was imported.
+    pub already_imported: Option<String>,
 }
 
-fn parse_local_dt(tz: &Tz, s: &str) -> anyhow::Result<i64> {
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Import every good line and report the bad ones.
+    #[default]
+    Lenient,
+    /// Import nothing if any line fails.
+    Strict,
+}
+
+pub(crate) fn parse_local_dt(tz: &Tz, s: &str) -> anyhow::Result<i64> {
     // Accept a few common formats.
     // 1) RFC3339 / ISO with offset -> parse as DateTime
     if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
@@ -72,6 +95,103 @@ fn parse_local_dt(tz: &Tz, s: &str) -> anyhow::Result<i64> {
     anyhow::bail!("unsupported datetime format: {s}")
 }
 
+fn local_to_utc_ms(tz: &Tz, ndt: &NaiveDateTime) -> anyhow::Result<i64> {
+    let local = tz
+        .from_local_datetime(ndt)
+        .single()
+        .context("ambiguous local time")?;
+    Ok(local.with_timezone(&Utc).timestamp_millis())
+}
+
+/// Parse a wall-clock time in `tz` using the first of `formats` that matches.
+pub(crate) fn parse_local_with_formats(tz: &Tz, s: &str, formats: &[&str]) -> anyhow::Result<i64> {
+    let s = s.trim();
+    for fmt in formats {
+        if let Ok(ndt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return local_to_utc_ms(tz, &ndt);
+        }
+    }
+    parse_local_dt(tz, s)
+}
+
+/// Parse broker-formatted money/number cells: "$1,234.50", "(12.50)", "-$3.10",
+/// "$(12.50)", "1.234,50 €", "12,50".
+pub(crate) fn parse_money(s: &str) -> anyhow::Result<f64> {
+    let raw = s.trim();
+    if raw.is_empty() {
+        anyhow::bail!("empty amount");
+    }
+    let negative = raw.starts_with('-') || raw.ends_with('-') || (raw.contains('(') && raw.contains(')'));
+
+    let digits: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect();
+    if !digits.chars().any(|c| c.is_ascii_digit()) {
+        anyhow::bail!("invalid amount: {raw}");
+    }
+
+    // Whichever separator comes last is the decimal point, unless a lone comma
+    // is followed by exactly three digits (US thousands, e.g. "1,234").
+    let normalized = match (digits.rfind('.'), digits.rfind(',')) {
+        (Some(dot), Some(comma)) if comma > dot => digits.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => digits.replace(',', ""),
+        (None, Some(comma)) if digits.matches(',').count() == 1 && digits.len() - comma - 1 != 3 => {
+            digits.replace(',', ".")
+        }
+        (None, Some(_)) => digits.replace(',', ""),
+        _ => digits,
+    };
+
+    let value: f64 = normalized
+        .parse()
+        .with_context(|| format!("invalid amount: {raw}"))?;
+    Ok(if negative { -value } else { value })
+}
+
+/// Case- and punctuation-insensitive header lookup for broker exports
+/// ("Market pos." matches "marketpos", "B/S" matches "bs").
+pub(crate) struct Columns {
+    index: std::collections::HashMap<String, usize>,
+}
+
+impl Columns {
+    pub(crate) fn new(headers: &csv::StringRecord) -> Self {
+        let index = headers
+            .iter()
+            .enumerate()
+            .map(|(i, h)| (normalize_header(h), i))
+            .collect();
+        Self { index }
+    }
+
+    pub(crate) fn has(&self, name: &str) -> bool {
+        self.index.contains_key(&normalize_header(name))
+    }
+
+    /// First non-empty cell among `names`.
+    pub(crate) fn get<'r>(&self, rec: &'r csv::StringRecord, names: &[&str]) -> Option<&'r str> {
+        names
+            .iter()
+            .filter_map(|n| self.index.get(&normalize_header(n)))
+            .filter_map(|i| rec.get(*i))
+            .map(str::trim)
+            .find(|v| !v.is_empty())
+    }
+
+    pub(crate) fn req<'r>(&self, rec: &'r csv::StringRecord, names: &[&str]) -> anyhow::Result<&'r str> {
+        self.get(rec, names)
+            .with_context(|| format!("missing value for column \"{}\"", names[0]))
+    }
+}
+
+fn normalize_header(h: &str) -> String {
+    h.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
 fn row_to_trade_input(row: &CsvRow, tz: &str) -> anyhow::Result<TradeInput> {
     let tz: Tz = tz.parse().context("invalid timezone")?;
 
@@ -100,51 +220,279 @@ fn row_to_trade_input(row: &CsvRow, tz: &str) -> anyhow::Result<TradeInput> {
         exit_time_utc: exit_ms,
         timezone: tz.to_string(),
         session: row.session.clone().unwrap_or_else(|| "other".to_string()),
-        pnl_amount: row.pnl_amount.unwrap_or(0.0),
+        entry_price: row.entry_price,
+        exit_price: row.exit_price,
+        // Without prices there is nothing to compute from; keep the old 0.0 default.
+        pnl_amount: row.pnl_amount.or_else(|| row.entry_price.is_none().then_some(0.0)),
         pnl_includes_fees: row.pnl_includes_fees.unwrap_or(true),
         fees: row.fees.unwrap_or(0.0),
         notes: row.notes.clone().unwrap_or_default(),
+        instrument: row.instrument.clone().filter(|s| !s.trim().is_empty()),
+        account_id: None,
+        tags: None,
         rules_checked: None,
     })
 }
 
-pub fn import_generic_csv(conn: &Connection, csv_path: &Path, tz: &str) -> anyhow::Result<CsvImportResult> {
+/// A parsed source line, ready to persist.
+pub(crate) enum ImportRecord {
+    /// A closed trade and the broker account label it belongs to ("" if the file has none).
+    Trade { input: Box<TradeInput>, account: String },
+    Execution(ExecutionInput),
+    /// A fill whose report also describes the contract; the instrument is added
+    /// to the catalog with the fill if its root is unknown. Existing catalog
+    /// rows are never changed.
+    ExecutionWithInstrument { input: ExecutionInput, instrument: Box<Instrument> },
+}
+
+/// One source line: its 1-based line number and what it parsed into.
+/// `Ok(None)` means the line was deliberately skipped (blank, summary row, ...).
+pub(crate) type ParsedLine = (usize, anyhow::Result<Option<ImportRecord>>);
+
+pub(crate) fn open_csv(csv_path: &Path) -> anyhow::Result<csv::Reader<std::fs::File>> {
     let file = std::fs::File::open(csv_path).with_context(|| format!("open csv {}", csv_path.display()))?;
-    let mut rdr = csv::ReaderBuilder::new()
+    Ok(csv::ReaderBuilder::new()
         .flexible(true)
         .trim(csv::Trim::All)
-        .from_reader(file);
+        .from_reader(file))
+}
+
+enum Persisted {
+    Trade,
+    Execution,
+    /// A fill whose contract root was added to the catalog as a placeholder.
+    ExecutionWithPlaceholder(String),
+    Duplicate,
+}
+
+fn persist(conn: &Connection, source: &str, import_id: &str, record: ImportRecord) -> anyhow::Result<Persisted> {
+    match record {
+        ImportRecord::Trade { mut input, account } => {
+            let fingerprint = crate::imports::trade_fingerprint(source, &account, &input);
+            if crate::imports::fingerprint_exists(conn, &fingerprint)? {
+                return Ok(Persisted::Duplicate);
+            }
+            if input.account_id.is_none() {
+                input.account_id = crate::accounts::account_for_label(conn, &account, source)?;
+            }
+            let trade = crate::trades::create_trade(conn, *input).context("failed to create trade")?;
+            crate::imports::tag_trade(conn, &trade.id, import_id, &fingerprint)?;
+            Ok(Persisted::Trade)
+        }
+        ImportRecord::Execution(input) => {
+            // Create the account now so it is attributed to this broker; build_trades links it.
+            crate::accounts::account_for_label(conn, &input.account, source)?;
+            // Fills with a broker id are deduped by idx_executions_external on insert.
+            let fingerprint = if input.external_id.trim().is_empty() {
+                let fingerprint = crate::imports::execution_fingerprint(&input)?;
+                if crate::imports::execution_fingerprint_exists(conn, &fingerprint, import_id)? {
+                    return Ok(Persisted::Duplicate);
+                }
+                Some(fingerprint)
+            } else {
+                None
+            };
+            match crate::executions::insert_execution(conn, &input).context("failed to store execution")? {
+                StoredExecution::Inserted(id) => {
+                    crate::imports::tag_execution(conn, &id, import_id, fingerprint.as_deref())?;
+                    Ok(Persisted::Execution)
+                }
+                // Stays with the import that first brought it in.
+                StoredExecution::Updated(_) => Ok(Persisted::Execution),
+                StoredExecution::Duplicate => Ok(Persisted::Duplicate),
+            }
+        }
+        ImportRecord::ExecutionWithInstrument { input, instrument } => {
+            // Inside the line's savepoint, so a failed line or strict rollback leaves no catalog row.
+            let added = crate::instruments::resolve_instrument(conn, &input.symbol)?.is_none()
+                && crate::instruments::insert_instrument_if_missing(conn, &instrument)?;
+            match persist(conn, source, import_id, ImportRecord::Execution(input))? {
+                Persisted::Execution if added => Ok(Persisted::ExecutionWithPlaceholder(instrument.root_symbol.to_uppercase())),
+                other => Ok(other),
+            }
+        }
+    }
+}
+
+/// Shared tail of every importer: record the batch, persist parsed lines
+/// (skipping trades and fills seen in earlier imports), then pair any imported
+/// fills into round-trip trades. A file identical to an earlier import of the
+/// same source is not imported again.
+///
+/// Everything runs in one transaction. Each line gets its own savepoint, so in
+/// lenient mode a failing line leaves nothing behind; in strict mode any error
+/// rolls back the whole file.
+pub(crate) fn run_import(
+    conn: &Connection,
+    source: &str,
+    path: &Path,
+    lines: Vec<ParsedLine>,
+    tz: &str,
+    method: MatchMethod,
+    mode: ImportMode,
+) -> anyhow::Result<CsvImportResult> {
+    let rows_total = lines.len() as i64;
+    let file_hash = crate::imports::file_hash(path)?;
+    if let Some(earlier) = crate::imports::find_identical_import(conn, source, &file_hash)? {
+        return Ok(CsvImportResult {
+            import_id: None,
+            created: 0,
+            skipped: rows_total,
+            errors: Vec::new(),
+            rolled_back: false,
+            placeholder_instruments: Vec::new(),
+            already_imported: Some(earlier),
+        });
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let import_id = crate::imports::start_import(&tx, source, path, &file_hash)?;
 
     let mut created = 0i64;
     let mut skipped = 0i64;
     let mut errors: Vec<String> = Vec::new();
+    let mut executions = 0i64;
+    let mut placeholder_instruments: Vec<String> = Vec::new();
 
-    for (i, rec) in rdr.deserialize::<CsvRow>().enumerate() {
-        let line = i + 2; // header is line 1
-        match rec {
-            Ok(row) => {
-                if row.symbol.trim().is_empty() {
-                    skipped += 1;
-                    continue;
-                }
-                match row_to_trade_input(&row, tz) {
-                    Ok(input) => {
-                        if let Err(e) = crate::trades::create_trade(conn, input) {
-                            errors.push(format!("line {line}: failed to create trade: {e}"));
-                        } else {
-                            created += 1;
-                        }
-                    }
-                    Err(e) => errors.push(format!("line {line}: {e}")),
-                }
+    for (line, parsed) in lines {
+        let record = match parsed {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("line {line}: {e}"));
+                continue;
+            }
+        };
+        match crate::db::with_savepoint(&tx, || persist(&tx, source, &import_id, record)) {
+            Ok(Persisted::Trade) => created += 1,
+            Ok(Persisted::Execution) => executions += 1,
+            Ok(Persisted::ExecutionWithPlaceholder(root)) => {
+                executions += 1;
+                placeholder_instruments.push(root);
             }
-            Err(e) => errors.push(format!("line {line}: {e}")),
+            Ok(Persisted::Duplicate) => skipped += 1, // already imported
+            Err(e) => errors.push(format!("line {line}: {e:#}")),
         }
     }
 
-    Ok(CsvImportResult {
+    if executions > 0 {
+        let built = crate::executions::build_trades(&tx, tz, method, Some(&import_id))?;
+        crate::imports::tag_built_trades(&tx, &import_id)?;
+        created += built.created;
+        errors.extend(built.errors);
+    }
+
+    if mode == ImportMode::Strict && !errors.is_empty() {
+        tx.rollback()?;
+        return Ok(CsvImportResult {
+            import_id: None,
+            created: 0,
+            skipped,
+            errors,
+            rolled_back: true,
+            placeholder_instruments: Vec::new(),
+            already_imported: None,
+        });
+    }
+
+    let result = CsvImportResult {
+        import_id: Some(import_id),
         created,
         skipped,
         errors,
-    })
+        rolled_back: false,
+        placeholder_instruments,
+        already_imported: None,
+    };
+    crate::imports::finish_import(&tx, &result, rows_total)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Parse a generic CSV, either by its fixed headers or through an import profile.
+/// Returns the timezone the rows were read in along with the lines.
+fn generic_lines(
+    conn: &Connection,
+    csv_path: &Path,
+    tz: &str,
+    profile_id: Option<&str>,
+    limit: Option<usize>,
+) -> anyhow::Result<(String, Vec<ParsedLine>)> {
+    let mut rdr = open_csv(csv_path)?;
+    let limit = limit.unwrap_or(usize::MAX);
+
+    let mut lines: Vec<ParsedLine> = Vec::new();
+    let Some(profile_id) = profile_id else {
+        for (i, rec) in rdr.deserialize::<CsvRow>().take(limit).enumerate() {
+            let line = i + 2; // header is line 1
+            let parsed = rec.map_err(anyhow::Error::from).and_then(|row| {
+                if row.symbol.trim().is_empty() {
+                    return Ok(None);
+                }
+                let account = row.account.clone().unwrap_or_default();
+                row_to_trade_input(&row, tz).map(|input| Some(ImportRecord::Trade { input: Box::new(input), account }))
+            });
+            lines.push((line, parsed));
+        }
+        return Ok((tz.to_string(), lines));
+    };
+
+    let profile = crate::import_profiles::get_profile(conn, profile_id)?;
+    let tz = profile
+        .timezone
+        .clone()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| tz.to_string());
+    let reader = crate::import_profiles::ProfileReader::new(&profile, rdr.headers()?, tz.parse().context("invalid timezone")?)?;
+    for (i, rec) in rdr.records().take(limit).enumerate() {
+        let line = i + 2; // header is line 1
+        let parsed = rec
+            .map_err(anyhow::Error::from)
+            .and_then(|rec| reader.trade_input(&rec))
+            .map(|row| row.map(|(input, account)| ImportRecord::Trade { input: Box::new(input), account }));
+        lines.push((line, parsed));
+    }
+    Ok((tz, lines))
+}
+
+pub fn import_generic_csv(
+    conn: &Connection,
+    csv_path: &Path,
+    tz: &str,
+    profile_id: Option<&str>,
+    mode: ImportMode,
+) -> anyhow::Result<CsvImportResult> {
+    let (tz, lines) = generic_lines(conn, csv_path, tz, profile_id, None)?;
+    run_import(conn, "generic", csv_path, lines, &tz, MatchMethod::Fifo, mode)
+}
+
+/// A source line as it would be imported, without writing anything.
+#[derive(Debug, serde::Serialize)]
+pub struct CsvPreviewRow {
+    pub line: usize,
+    pub trade: Option<TradeInput>, // None with no error = skipped
+    pub error: Option<String>,
+}
+
+pub fn preview_generic_csv(
+    conn: &Connection,
+    csv_path: &Path,
+    tz: &str,
+    profile_id: Option<&str>,
+    limit: usize,
+) -> anyhow::Result<Vec<CsvPreviewRow>> {
+    let (_, lines) = generic_lines(conn, csv_path, tz, profile_id, Some(limit))?;
+
+    let mut out = Vec::new();
+    for (line, parsed) in lines {
+        out.push(match parsed {
+            Ok(Some(ImportRecord::Trade { input: trade, .. })) => CsvPreviewRow { line, trade: Some(*trade), error: None },
+            Ok(_) => CsvPreviewRow { line, trade: None, error: None },
+            Err(e) => CsvPreviewRow { line, trade: None, error: Some(e.to_string()) },
+        });
+    }
+    Ok(out)
 }
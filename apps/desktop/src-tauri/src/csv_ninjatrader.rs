@@ -0,0 +1,167 @@
+use anyhow::Context;
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use std::path::Path;
+
+use crate::csv_import::{open_csv, parse_local_with_formats, parse_money, run_import, Columns, CsvImportResult, ImportMode, ImportRecord, ParsedLine};
+use crate::matcher::MatchMethod;
+use crate::models::{ExecutionInput, TradeInput};
+
+const SOURCE: &str = "ninjatrader";
+
+// NT8 writes dates in the Windows display locale. Month-first (en-US) is the default;
+// dotted dates are always day-first.
+const MONTH_FIRST_FORMATS: &[&str] = &[
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %I:%M %p",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+    "%d.%m.%Y %H:%M:%S",
+    "%d.%m.%Y %H:%M",
+];
+const DAY_FIRST_FORMATS: &[&str] = &[
+    "%d/%m/%Y %H:%M:%S",
+    "%d/%m/%Y %H:%M",
+    "%d/%m/%Y %I:%M:%S %p",
+    "%d.%m.%Y %H:%M:%S",
+    "%d.%m.%Y %H:%M",
+    "%d-%m-%Y %H:%M:%S",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct NinjaTraderOptions {
+    /// Dates like 03/04/2025 are 3 April rather than 4 March.
+    pub day_first: bool,
+    pub method: MatchMethod,
+    pub mode: ImportMode,
+}
+
+enum ExportKind {
+    Trades,
+    Executions,
+}
+
+/// Import a NinjaTrader 8 Trade Performance "Trades" grid export or an "Executions" export.
+pub fn import_ninjatrader_csv(
+    conn: &Connection,
+    csv_path: &Path,
+    tz_name: &str,
+    opts: NinjaTraderOptions,
+) -> anyhow::Result<CsvImportResult> {
+    let tz: Tz = tz_name.parse().context("invalid timezone")?;
+    let mut rdr = open_csv(csv_path)?;
+    let cols = Columns::new(rdr.headers()?);
+
+    let kind = if cols.has("Market pos.") && cols.has("Entry price") {
+        ExportKind::Trades
+    } else if cols.has("Action") && cols.has("Price") && cols.has("Time") {
+        ExportKind::Executions
+    } else {
+        anyhow::bail!("not a NinjaTrader Trades or Executions export (unrecognized headers)");
+    };
+
+    let formats = if opts.day_first { DAY_FIRST_FORMATS } else { MONTH_FIRST_FORMATS };
+
+    let mut lines: Vec<ParsedLine> = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let line = i + 2; // header is line 1
+        let parsed = rec.map_err(anyhow::Error::from).and_then(|rec| {
+            // NT appends blank/summary lines; anything without an instrument is not a row.
+            if cols.get(&rec, &["Instrument"]).is_none() {
+                return Ok(None);
+            }
+            let record = match kind {
+                ExportKind::Trades => ImportRecord::Trade {
+                    input: Box::new(trade_row(conn, &cols, &rec, &tz, formats)?),
+                    account: cols.get(&rec, &["Account"]).unwrap_or_default().to_string(),
+                },
+                ExportKind::Executions => ImportRecord::Execution(execution_row(&cols, &rec, &tz, formats)?),
+            };
+            Ok(Some(record))
+        });
+        lines.push((line, parsed));
+    }
+
+    run_import(conn, SOURCE, csv_path, lines, tz_name, opts.method, opts.mode)
+}
+
+fn trade_row(
+    conn: &Connection,
+    cols: &Columns,
+    rec: &csv::StringRecord,
+    tz: &Tz,
+    formats: &[&str],
+) -> anyhow::Result<TradeInput> {
+    let symbol = nt_symbol(cols.req(rec, &["Instrument"])?)?;
+    let side = match cols.req(rec, &["Market pos."])?.to_ascii_lowercase().as_str() {
+        "long" => "long",
+        "short" => "short",
+        other => anyhow::bail!("unsupported market position: {other}"),
+    };
+
+    let fees = ["Commission", "Clearing Fee", "Exchange fee", "IP fee", "NFA fee"]
+        .iter()
+        .filter_map(|c| cols.get(rec, &[c]))
+        .map(parse_money)
+        .sum::<anyhow::Result<f64>>()?
+        .abs();
+
+    // Leave PnL to be computed from prices; only fall back to NT's Profit
+    // (gross of commission) when the contract is not in our catalog.
+    let pnl_amount = match cols.get(rec, &["Profit"]) {
+        Some(p) if crate::instruments::resolve_instrument(conn, &symbol)?.is_none() => Some(parse_money(p)?),
+        _ => None,
+    };
+
+    Ok(TradeInput {
+        market: "futures".to_string(),
+        symbol,
+        side: side.to_string(),
+        qty: parse_money(cols.req(rec, &["Qty", "Quantity"])?)?.abs(),
+        entry_time_utc: parse_local_with_formats(tz, cols.req(rec, &["Entry time"])?, formats)?,
+        exit_time_utc: parse_local_with_formats(tz, cols.req(rec, &["Exit time"])?, formats)?,
+        timezone: tz.to_string(),
+        session: "other".to_string(),
+        entry_price: Some(parse_money(cols.req(rec, &["Entry price"])?)?),
+        exit_price: Some(parse_money(cols.req(rec, &["Exit price"])?)?),
+        pnl_amount,
+        pnl_includes_fees: false,
+        fees,
+        notes: String::new(),
+        instrument: None,
+        account_id: None,
+        tags: None,
+        rules_checked: None,
+    })
+}
+
+fn execution_row(cols: &Columns, rec: &csv::StringRecord, tz: &Tz, formats: &[&str]) -> anyhow::Result<ExecutionInput> {
+    Ok(ExecutionInput {
+        source: SOURCE.to_string(),
+        external_id: cols.get(rec, &["ID", "Execution ID"]).unwrap_or_default().to_string(),
+        account: cols.get(rec, &["Account"]).unwrap_or_default().to_string(),
+        symbol: nt_symbol(cols.req(rec, &["Instrument"])?)?,
+        side: cols.req(rec, &["Action"])?.to_string(),
+        qty: parse_money(cols.req(rec, &["Quantity", "Qty"])?)?.abs(),
+        price: parse_money(cols.req(rec, &["Price"])?)?,
+        fees: cols.get(rec, &["Commission"]).map(parse_money).transpose()?.unwrap_or(0.0).abs(),
+        realized_pnl: None,
+        time_utc: parse_local_with_formats(tz, cols.req(rec, &["Time"])?, formats)?,
+    })
+}
+
+/// "MNQ 03-25" -> "MNQH5". Names without an expiry (e.g. continuous "MNQ ##-##") keep their root.
+fn nt_symbol(name: &str) -> anyhow::Result<String> {
+    let mut parts = name.split_whitespace();
+    let root = parts.next().context("empty instrument name")?;
+    let Some(expiry) = parts.next() else {
+        return Ok(root.to_uppercase());
+    };
+    let Some((mm, yy)) = expiry.split_once('-') else {
+        return Ok(root.to_uppercase());
+    };
+    match (mm.parse::<u32>(), yy.parse::<i32>()) {
+        (Ok(month), Ok(year)) => crate::instruments::contract_symbol(root, month, year),
+        _ => Ok(root.to_uppercase()),
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::Context;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::csv_import::{parse_local_with_formats, parse_money, run_import, Columns, CsvImportResult, ImportMode, ImportRecord, ParsedLine};
+use crate::matcher::MatchMethod;
+use crate::models::ExecutionInput;
+
+const SOURCE: &str = "rithmic";
+
+const TIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%Y%m%d %H:%M:%S",
+];
+
+/// Import an R|Trader Pro "Order History" or "Completed Orders" export.
+///
+/// Each order with a filled quantity becomes one execution at its average fill
+/// price; fills are then paired into trades per account. An order listed more
+/// than once (partially filled, then filled) counts once, with its largest fill.
+/// Orders already imported (same account and order number) are skipped.
+pub fn import_rithmic_csv(
+    conn: &Connection,
+    csv_path: &Path,
+    tz_name: &str,
+    method: MatchMethod,
+    mode: ImportMode,
+) -> anyhow::Result<CsvImportResult> {
+    let tz: Tz = tz_name.parse().context("invalid timezone")?;
+    let file = std::fs::File::open(csv_path).with_context(|| format!("open csv {}", csv_path.display()))?;
+    let lines = parse_lines(file, &tz)?;
+    run_import(conn, SOURCE, csv_path, lines, tz_name, method, mode)
+}
+
+fn parse_lines(reader: impl std::io::Read, tz: &Tz) -> anyhow::Result<Vec<ParsedLine>> {
+    // R|Trader puts a title line ("Completed Orders", export time, ...) above the header row.
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let mut cols: Option<Columns> = None;
+    let mut lines: Vec<ParsedLine> = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let line = i + 1;
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                lines.push((line, Err(e.into())));
+                continue;
+            }
+        };
+        let Some(c) = &cols else {
+            let candidate = Columns::new(&rec);
+            if candidate.has("Account") && candidate.has("Buy/Sell") && candidate.has("Symbol") {
+                cols = Some(candidate);
+            }
+            continue;
+        };
+        lines.push((line, order_row(c, &rec, tz).map(|r| r.map(ImportRecord::Execution))));
+    }
+    if cols.is_none() {
+        anyhow::bail!("not an R|Trader Pro order history export (no Account / Buy/Sell / Symbol header row)");
+    }
+
+    keep_final_fills(&mut lines);
+    Ok(lines)
+}
+
+/// Order History repeats an order on every status change. Keep the row with the
+/// largest filled quantity (the last one on a tie) and skip the others.
+fn keep_final_fills(lines: &mut [ParsedLine]) {
+    let mut best: HashMap<(String, String), (usize, f64)> = HashMap::new();
+    for (i, (_, parsed)) in lines.iter().enumerate() {
+        if let Ok(Some(ImportRecord::Execution(e))) = parsed {
+            if e.external_id.is_empty() {
+                continue;
+            }
+            let key = (e.account.clone(), e.external_id.clone());
+            match best.get(&key) {
+                Some((_, qty)) if *qty > e.qty => {}
+                _ => {
+                    best.insert(key, (i, e.qty));
+                }
+            }
+        }
+    }
+
+    for (i, (_, parsed)) in lines.iter_mut().enumerate() {
+        let superseded = match parsed {
+            Ok(Some(ImportRecord::Execution(e))) if !e.external_id.is_empty() => {
+                best.get(&(e.account.clone(), e.external_id.clone())).is_some_and(|(keep, _)| *keep != i)
+            }
+            _ => false,
+        };
+        if superseded {
+            *parsed = Ok(None);
+        }
+    }
+}
+
+fn order_row(cols: &Columns, rec: &csv::StringRecord, tz: &Tz) -> anyhow::Result<Option<ExecutionInput>> {
+    let Some(symbol) = cols.get(rec, &["Symbol"]) else {
+        return Ok(None);
+    };
+    // Cancelled and rejected orders show up with nothing filled.
+    let qty = match cols.get(rec, &["Qty Filled", "Filled Qty", "Fill Qty"]) {
+        Some(q) => parse_money(q)?.abs(),
+        None => 0.0,
+    };
+    if qty <= 0.0 {
+        return Ok(None);
+    }
+
+    // "Commission Fill Rate" is per contract; a plain "Commission" column is the total.
+    let fees = match cols.get(rec, &["Commission"]) {
+        Some(c) => parse_money(c)?.abs(),
+        None => cols
+            .get(rec, &["Commission Fill Rate"])
+            .map(parse_money)
+            .transpose()?
+            .map_or(0.0, |rate| rate.abs() * qty),
+    };
+
+    Ok(Some(ExecutionInput {
+        source: SOURCE.to_string(),
+        external_id: cols.get(rec, &["Order Number", "Order Id", "Order #"]).unwrap_or_default().to_string(),
+        account: cols.get(rec, &["Account"]).unwrap_or_default().to_string(),
+        symbol: symbol.to_uppercase(),
+        side: cols.req(rec, &["Buy/Sell"])?.to_string(),
+        qty,
+        price: parse_money(cols.req(rec, &["Avg Fill Price", "Fill Price", "Price"])?)?,
+        fees,
+        realized_pnl: None,
+        time_utc: parse_time(tz, cols.req(rec, &["Fill Time", "Update Time", "Create Time"])?)?,
+    }))
+}
+
+/// Times may carry a trailing zone label ("2024-10-04 09:31:02 CDT"). A
+/// label is honoured as the offset it names; only unlabelled times are read in
+/// the configured timezone.
+fn parse_time(tz: &Tz, s: &str) -> anyhow::Result<i64> {
+    let s = s.trim();
+    match s.rsplit_once(' ') {
+        Some((head, label))
+            if label.chars().all(|c| c.is_ascii_alphabetic())
+                && !label.eq_ignore_ascii_case("am")
+                && !label.eq_ignore_ascii_case("pm") =>
+        {
+            let offset_hours = zone_label_offset(label).with_context(|| format!("unknown timezone label {label} in {s}"))?;
+            let ndt = TIME_FORMATS
+                .iter()
+                .find_map(|fmt| NaiveDateTime::parse_from_str(head.trim(), fmt).ok())
+                .with_context(|| format!("invalid time: {s}"))?;
+            Ok((ndt - chrono::Duration::hours(offset_hours)).and_utc().timestamp_millis())
+        }
+        _ => parse_local_with_formats(tz, s, TIME_FORMATS),
+    }
+}
+
+/// UTC offset in hours of the zone abbreviations Rithmic exports use.
+fn zone_label_offset(label: &str) -> Option<i64> {
+    let hours = match label.to_ascii_uppercase().as_str() {
+        "UTC" | "GMT" | "Z" => 0,
+        "EDT" => -4,
+        "EST" | "CDT" => -5,
+        "CST" | "MDT" => -6,
+        "MST" | "PDT" => -7,
+        "PST" => -8,
+        "BST" | "CET" => 1,
+        "CEST" => 2,
+        _ => return None,
+    };
+    Some(hours)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_order_keeps_final_fill() {
+        let tz: Tz = "America/Chicago".parse().unwrap();
+        let csv = include_str!("../tests/fixtures/rithmic_order_history_partial_fills.csv");
+        let lines = parse_lines(csv.as_bytes(), &tz).unwrap();
+
+        let fills: Vec<(usize, String, f64, f64)> = lines
+            .iter()
+            .filter_map(|(line, parsed)| match parsed {
+                Ok(Some(ImportRecord::Execution(e))) => Some((*line, e.external_id.clone(), e.qty, e.price)),
+                _ => None,
+            })
+            .collect();
+        // 1001: partial 1 then filled 3 -> the filled row. 1002: one row. 1003: cancelled, nothing filled.
+        assert_eq!(
+            fills,
+            vec![(4, "1001".to_string(), 3.0, 5001.25), (5, "1002".to_string(), 3.0, 5004.0)]
+        );
+    }
+
+    #[test]
+    fn zone_label_overrides_configured_timezone() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        // 16:30 CDT is 21:30 UTC, even though the app is set to New York.
+        let labelled = parse_time(&new_york, "2024-10-04 16:30:00 CDT").unwrap();
+        assert_eq!(labelled, 1728077400000);
+        // Without a label the configured zone applies: 16:30 EDT is 20:30 UTC.
+        assert_eq!(parse_time(&new_york, "2024-10-04 16:30:00").unwrap(), 1728073800000);
+        assert_eq!(parse_time(&new_york, "10/04/2024 04:30:00 PM").unwrap(), 1728073800000);
+        assert!(parse_time(&new_york, "2024-10-04 16:30:00 XYZT").is_err());
+    }
+}
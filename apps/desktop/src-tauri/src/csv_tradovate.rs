@@ -0,0 +1,242 @@
+use anyhow::Context;
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use std::path::Path;
+
+use crate::csv_import::{open_csv, parse_local_with_formats, parse_money, run_import, Columns, CsvImportResult, ImportMode, ImportRecord, ParsedLine};
+use crate::matcher::MatchMethod;
+use crate::models::{ExecutionInput, TradeInput};
+
+const SOURCE: &str = "tradovate";
+const ORDER_SEP: char = ':';
+
+// Tradovate writes US-style wall-clock times in the account's display timezone.
+const TIME_FORMATS: &[&str] = &[
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M:%S%.f",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+];
+
+enum ExportKind {
+    /// "Performance" / "Position History": one row per paired buy and sell fill.
+    Pairs,
+    Orders,
+    Fills,
+}
+
+/// Import a Tradovate (or Tradovate-backed prop firm) "Orders", "Fills",
+/// "Performance" or "Position History" export.
+pub fn import_tradovate_csv(
+    conn: &Connection,
+    csv_path: &Path,
+    tz_name: &str,
+    method: MatchMethod,
+    mode: ImportMode,
+) -> anyhow::Result<CsvImportResult> {
+    let tz: Tz = tz_name.parse().context("invalid timezone")?;
+    let mut rdr = open_csv(csv_path)?;
+    let cols = Columns::new(rdr.headers()?);
+
+    // Header matching ignores case and punctuation, so "buyPrice" and "Buy Price" are the same column.
+    let kind = if cols.has("buyPrice") && cols.has("sellPrice") && cols.has("boughtTimestamp") {
+        ExportKind::Pairs
+    } else if cols.has("B/S") && cols.has("Status") {
+        ExportKind::Orders
+    } else if (cols.has("B/S") || cols.has("_action")) && (cols.has("Price") || cols.has("_price")) {
+        ExportKind::Fills
+    } else {
+        anyhow::bail!("not a Tradovate Orders, Fills or Performance export (unrecognized headers)");
+    };
+
+    let mut lines: Vec<ParsedLine> = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let line = i + 2; // header is line 1
+        let parsed = rec.map_err(anyhow::Error::from).and_then(|rec| {
+            let record = match kind {
+                ExportKind::Pairs => pair_row(conn, &cols, &rec, &tz)?.map(|input| ImportRecord::Trade {
+                    input: Box::new(input),
+                    account: cols.get(&rec, &["Account"]).unwrap_or_default().to_string(),
+                }),
+                ExportKind::Orders => order_row(&cols, &rec, &tz)?.map(ImportRecord::Execution),
+                ExportKind::Fills => fill_row(&cols, &rec, &tz)?.map(ImportRecord::Execution),
+            };
+            if let Some(ImportRecord::Execution(e)) = &record {
+                ensure_single_export(conn, &kind, e)?;
+            }
+            Ok(record)
+        });
+        lines.push((line, parsed));
+    }
+
+    run_import(conn, SOURCE, csv_path, lines, tz_name, method, mode)
+}
+
+fn pair_row(conn: &Connection, cols: &Columns, rec: &csv::StringRecord, tz: &Tz) -> anyhow::Result<Option<TradeInput>> {
+    let Some(symbol) = contract(cols, rec) else {
+        return Ok(None);
+    };
+
+    let bought_at = parse_time(tz, cols.req(rec, &["boughtTimestamp"])?)?;
+    let sold_at = parse_time(tz, cols.req(rec, &["soldTimestamp"])?)?;
+    let buy_price = parse_money(cols.req(rec, &["buyPrice"])?)?;
+    let sell_price = parse_money(cols.req(rec, &["sellPrice"])?)?;
+
+    let long = match cols.get(rec, &["side", "Direction"]).map(str::to_ascii_lowercase).as_deref() {
+        Some("long" | "buy" | "b") => true,
+        Some("short" | "sell" | "s") => false,
+        // Otherwise whichever fill came first opened the position. Both fills in
+        // the same second is common for fast scalps; the lower fill id was first.
+        _ if bought_at != sold_at => bought_at < sold_at,
+        _ => match (cols.get(rec, &["buyFillId"]), cols.get(rec, &["sellFillId"])) {
+            (Some(buy), Some(sell)) => match (buy.parse::<u64>(), sell.parse::<u64>()) {
+                (Ok(buy), Ok(sell)) => buy <= sell,
+                _ => buy <= sell,
+            },
+            _ => true,
+        },
+    };
+    let (entry_time, exit_time, entry_price, exit_price) = if long {
+        (bought_at, sold_at, buy_price, sell_price)
+    } else {
+        (sold_at, bought_at, sell_price, buy_price)
+    };
+
+    // Tradovate's P/L is before commissions. Only use it when we can't compute from prices.
+    let pnl_amount = match cols.get(rec, &["pnl", "P/L"]) {
+        Some(p) if crate::instruments::resolve_instrument(conn, &symbol)?.is_none() => Some(parse_money(p)?),
+        _ => None,
+    };
+
+    Ok(Some(TradeInput {
+        market: "futures".to_string(),
+        symbol,
+        side: if long { "long" } else { "short" }.to_string(),
+        qty: parse_money(cols.req(rec, &["qty", "Paired Qty"])?)?.abs(),
+        entry_time_utc: entry_time,
+        exit_time_utc: exit_time,
+        timezone: tz.to_string(),
+        session: "other".to_string(),
+        entry_price: Some(entry_price),
+        exit_price: Some(exit_price),
+        pnl_amount,
+        pnl_includes_fees: false,
+        fees: commission(cols, rec)?,
+        notes: String::new(),
+        instrument: None,
+        account_id: None,
+        tags: None,
+        rules_checked: None,
+    }))
+}
+
+fn order_row(cols: &Columns, rec: &csv::StringRecord, tz: &Tz) -> anyhow::Result<Option<ExecutionInput>> {
+    // Only filled orders turn into fills; working/cancelled/rejected ones are skipped.
+    let status = cols.get(rec, &["Status"]).unwrap_or_default();
+    if !status.eq_ignore_ascii_case("filled") {
+        return Ok(None);
+    }
+    let Some(symbol) = contract(cols, rec) else {
+        return Ok(None);
+    };
+
+    Ok(Some(ExecutionInput {
+        source: SOURCE.to_string(),
+        external_id: cols.get(rec, &["Order ID", "orderId"]).unwrap_or_default().to_string(),
+        account: cols.get(rec, &["Account"]).unwrap_or_default().to_string(),
+        symbol,
+        side: cols.req(rec, &["B/S"])?.to_string(),
+        qty: parse_money(cols.req(rec, &["Filled Qty", "filledQty", "Quantity"])?)?.abs(),
+        price: parse_money(cols.req(rec, &["Avg Fill Price", "avgPrice"])?)?,
+        fees: commission(cols, rec)?,
+        realized_pnl: None,
+        time_utc: parse_time(tz, cols.req(rec, &["Fill Time", "Timestamp"])?)?,
+    }))
+}
+
+fn fill_row(cols: &Columns, rec: &csv::StringRecord, tz: &Tz) -> anyhow::Result<Option<ExecutionInput>> {
+    let Some(symbol) = contract(cols, rec) else {
+        return Ok(None);
+    };
+
+    // "_action" is the raw API enum: 0 = buy, 1 = sell.
+    let side = match cols.get(rec, &["B/S"]) {
+        Some(s) => s.to_string(),
+        None => match cols.req(rec, &["_action"])? {
+            "0" => "buy".to_string(),
+            "1" => "sell".to_string(),
+            other => other.to_string(),
+        },
+    };
+
+    // Fills are keyed "<order id>:<fill id>" so they can be told apart from the
+    // order-level rows an Orders export stores under the bare order id.
+    let fill_id = cols.get(rec, &["Fill ID", "_id"]).unwrap_or_default();
+    let external_id = match cols.get(rec, &["Order ID", "_orderId"]) {
+        Some(order_id) if !fill_id.is_empty() => format!("{order_id}{ORDER_SEP}{fill_id}"),
+        _ => fill_id.to_string(),
+    };
+
+    Ok(Some(ExecutionInput {
+        source: SOURCE.to_string(),
+        external_id,
+        account: cols.get(rec, &["Account"]).unwrap_or_default().to_string(),
+        symbol,
+        side,
+        qty: parse_money(cols.req(rec, &["Quantity", "_qty"])?)?.abs(),
+        price: parse_money(cols.req(rec, &["Price", "_price"])?)?,
+        fees: commission(cols, rec)?,
+        realized_pnl: None,
+        time_utc: parse_time(tz, cols.req(rec, &["Timestamp", "_timestamp"])?)?,
+    }))
+}
+
+/// Orders and Fills exports describe the same executions at different grain,
+/// so each order may only come in through one of them.
+fn ensure_single_export(conn: &Connection, kind: &ExportKind, e: &ExecutionInput) -> anyhow::Result<()> {
+    let (order_id, sql, other) = match kind {
+        ExportKind::Orders => (
+            e.external_id.as_str(),
+            "SELECT EXISTS(SELECT 1 FROM executions WHERE source = ?1 AND account = ?2
+                AND substr(external_id, 1, length(?3) + 1) = ?3 || ':')",
+            "Fills",
+        ),
+        _ => match e.external_id.split_once(ORDER_SEP) {
+            Some((order_id, _)) => (
+                order_id,
+                "SELECT EXISTS(SELECT 1 FROM executions WHERE source = ?1 AND account = ?2 AND external_id = ?3)",
+                "Orders",
+            ),
+            None => return Ok(()),
+        },
+    };
+    if order_id.is_empty() {
+        return Ok(());
+    }
+    let exists: bool = conn.query_row(sql, rusqlite::params![SOURCE, e.account.trim(), order_id], |r| r.get(0))?;
+    if exists {
+        anyhow::bail!("order {order_id} was already imported from a Tradovate {other} export; undo that import first");
+    }
+    Ok(())
+}
+
+/// Tradovate contract codes ("MESZ4") are already exchange-style; the catalog
+/// lookup decodes the month/year suffix. Rows without a contract are summary lines.
+fn contract(cols: &Columns, rec: &csv::StringRecord) -> Option<String> {
+    cols.get(rec, &["Contract", "symbol", "Product"])
+        .map(|s| s.trim_start_matches('/').to_uppercase())
+}
+
+fn commission(cols: &Columns, rec: &csv::StringRecord) -> anyhow::Result<f64> {
+    Ok(cols
+        .get(rec, &["commission", "Commissions", "Fees", "Fee"])
+        .map(parse_money)
+        .transpose()?
+        .unwrap_or(0.0)
+        .abs())
+}
+
+fn parse_time(tz: &Tz, s: &str) -> anyhow::Result<i64> {
+    parse_local_with_formats(tz, s, TIME_FORMATS)
+}
@@ -1,20 +1,33 @@
 use anyhow::Context;
 use parking_lot::Mutex;
 use rusqlite::{params, Connection};
+use sha2::Digest;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
-use crate::db_seed::seed_default_rules;
+use crate::config::CipherParams;
+use crate::db_seed::{seed_default_instruments, seed_default_rules};
 
 #[derive(Debug, Clone, Copy, serde::Serialize)]
 pub struct DbStatus {
     pub configured: bool,
     pub encrypted: bool,
     pub unlocked: bool,
+    /// The open database uses weaker cipher settings than new ones get; see `db_upgrade_cipher`.
+    pub cipher_outdated: bool,
+    /// Idle minutes before an encrypted database locks itself; `None` when off.
+    pub auto_lock_minutes: Option<u32>,
 }
 
 #[derive(Debug)]
 pub struct DbState {
     conn: Mutex<Option<Connection>>,
+    cipher: Mutex<CipherParams>,
+    // Opened with a raw key (recovery-key databases), which bypasses the KDF.
+    raw_key: Mutex<bool>,
+    // Last time a command used the connection; drives the idle auto-lock.
+    last_activity: Mutex<Instant>,
+    auto_lock_minutes: Mutex<Option<u32>>,
     pub encrypted: Mutex<bool>,
     pub db_path: Mutex<Option<String>>,
 }
@@ -23,6 +36,10 @@ impl Default for DbState {
     fn default() -> Self {
         Self {
             conn: Mutex::new(None),
+            cipher: Mutex::new(CipherParams::default()),
+            raw_key: Mutex::new(false),
+            last_activity: Mutex::new(Instant::now()),
+            auto_lock_minutes: Mutex::new(None),
             encrypted: Mutex::new(false),
             db_path: Mutex::new(None),
         }
@@ -34,10 +51,14 @@ impl DbState {
         let configured = self.db_path.lock().is_some();
         let encrypted = *self.encrypted.lock();
         let unlocked = self.conn.lock().is_some();
+        let cipher_outdated =
+            encrypted && unlocked && !*self.raw_key.lock() && self.cipher.lock().weaker_than(&CipherParams::default());
         DbStatus {
             configured,
             encrypted,
             unlocked,
+            cipher_outdated,
+            auto_lock_minutes: *self.auto_lock_minutes.lock(),
         }
     }
 
@@ -50,7 +71,39 @@ impl DbState {
         *self.conn.lock() = None;
     }
 
-    pub fn create_new(&self, path: &Path, encrypted: bool, passphrase: Option<&str>) -> anyhow::Result<()> {
+    pub fn set_auto_lock(&self, minutes: Option<u32>) {
+        *self.auto_lock_minutes.lock() = minutes.filter(|m| *m > 0);
+    }
+
+    /// Close an encrypted database that has not been used for the auto-lock
+    /// period. Returns whether it was locked.
+    pub fn lock_if_idle(&self, now: Instant) -> bool {
+        let Some(minutes) = *self.auto_lock_minutes.lock() else {
+            return false;
+        };
+        if !*self.encrypted.lock() {
+            return false;
+        }
+        // Holding the connection lock means no command is running while we decide.
+        let mut conn = self.conn.lock();
+        if conn.is_none() {
+            return false;
+        }
+        let idle = now.saturating_duration_since(*self.last_activity.lock());
+        if idle < Duration::from_secs(u64::from(minutes) * 60) {
+            return false;
+        }
+        *conn = None;
+        true
+    }
+
+    pub fn create_new(
+        &self,
+        path: &Path,
+        encrypted: bool,
+        passphrase: Option<&str>,
+        cipher: &CipherParams,
+    ) -> anyhow::Result<()> {
         if path.exists() {
             // Avoid accidental overwrite.
             anyhow::bail!("Database already exists at {}", path.display());
@@ -61,58 +114,135 @@ impl DbState {
                 .with_context(|| format!("create db directory {}", parent.display()))?;
         }
 
-        let conn = Connection::open(path).with_context(|| format!("open db at {}", path.display()))?;
-        set_pragmas(&conn, encrypted, passphrase)?;
-        migrate_to_v1(&conn)?;
+        let mut conn = Connection::open(path).with_context(|| format!("open db at {}", path.display()))?;
+        set_pragmas(&conn, encrypted, passphrase, cipher)?;
+        migrate(&mut conn)?;
 
+        self.set_opened(cipher, passphrase);
+        *self.last_activity.lock() = Instant::now();
         *self.conn.lock() = Some(conn);
         Ok(())
     }
 
-    pub fn open_existing(&self, path: &Path, encrypted: bool, passphrase: Option<&str>) -> anyhow::Result<()> {
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("create db directory {}", parent.display()))?;
+    pub fn open_existing(
+        &self,
+        path: &Path,
+        encrypted: bool,
+        passphrase: Option<&str>,
+        cipher: &CipherParams,
+    ) -> anyhow::Result<()> {
+        let conn = open_connection(path, encrypted, passphrase, cipher)?;
+
+        self.set_opened(cipher, passphrase);
+        *self.last_activity.lock() = Instant::now();
+        *self.conn.lock() = Some(conn);
+        Ok(())
+    }
+
+    /// Open an encrypted database whose cipher settings may differ from the
+    /// configured ones (e.g. a restored backup of an older database). Returns
+    /// the settings that worked; the error is the one for `preferred`.
+    pub fn unlock(&self, path: &Path, passphrase: Option<&str>, preferred: &CipherParams) -> anyhow::Result<CipherParams> {
+        let mut candidates = vec![preferred.clone()];
+        for c in [CipherParams::default(), CipherParams::legacy()] {
+            if !candidates.contains(&c) {
+                candidates.push(c);
+            }
         }
-        let conn = Connection::open(path).with_context(|| format!("open db at {}", path.display()))?;
-        set_pragmas(&conn, encrypted, passphrase)?;
 
-        // If encrypted and wrong key, this should fail when we touch the schema.
-        // Do a small read to force decryption.
-        let _ver: i64 = conn
-            .query_row("SELECT 1", [], |row| row.get(0))
-            .context("db probe query")?;
+        let mut first_err = None;
+        for cipher in candidates {
+            match self.open_existing(path, true, passphrase, &cipher) {
+                Ok(()) => return Ok(cipher),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_err.expect("at least one candidate"))
+    }
 
-        // Ensure schema exists; if not, initialize.
-        migrate_to_v1(&conn)?;
+    /// Run `f` with the connection lock held throughout, so no command can use
+    /// the database between the steps of a file swap. `f` may take the
+    /// connection out of the slot and put another (see `open_connection`) back.
+    pub fn with_conn_slot<T>(&self, f: impl FnOnce(&mut Option<Connection>) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let mut guard = self.conn.lock();
+        let out = f(&mut guard);
+        *self.last_activity.lock() = Instant::now();
+        out
+    }
 
-        *self.conn.lock() = Some(conn);
-        Ok(())
+    /// Record how a connection was keyed, including one installed via `with_conn_slot`.
+    pub(crate) fn set_opened(&self, cipher: &CipherParams, passphrase: Option<&str>) {
+        *self.cipher.lock() = cipher.clone();
+        // SQLCipher reads a key of the form x'<hex>' as raw key bytes.
+        *self.raw_key.lock() = passphrase.is_some_and(|p| p.starts_with("x'") && p.ends_with('\''));
     }
 
     pub fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> anyhow::Result<T>) -> anyhow::Result<T> {
         let guard = self.conn.lock();
         let conn = guard.as_ref().context("database is locked")?;
-        f(conn)
+        *self.last_activity.lock() = Instant::now();
+        let out = f(conn);
+        // Long imports count as activity until they finish.
+        *self.last_activity.lock() = Instant::now();
+        out
+    }
+}
+
+/// Open an existing database, check the key and bring its schema up to date.
+pub(crate) fn open_connection(
+    path: &Path,
+    encrypted: bool,
+    passphrase: Option<&str>,
+    cipher: &CipherParams,
+) -> anyhow::Result<Connection> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("create db directory {}", parent.display()))?;
+    }
+    let mut conn = Connection::open(path).with_context(|| format!("open db at {}", path.display()))?;
+    set_pragmas(&conn, encrypted, passphrase, cipher)?;
+
+    // If encrypted and wrong key, this should fail when we touch the schema.
+    // Do a small read to force decryption.
+    let _ver: i64 = conn
+        .query_row("SELECT 1", [], |row| row.get(0))
+        .context("db probe query")?;
+
+    // Bring the schema up to date (or refuse if it is from a newer build).
+    migrate(&mut conn)?;
+    Ok(conn)
+}
+
+/// Run `f` inside a SAVEPOINT so a failure undoes only its own writes.
+pub fn with_savepoint<T>(conn: &Connection, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    conn.execute_batch("SAVEPOINT sp")?;
+    match f() {
+        Ok(v) => {
+            conn.execute_batch("RELEASE sp")?;
+            Ok(v)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO sp; RELEASE sp")?;
+            Err(e)
+        }
     }
 }
 
-fn set_pragmas(conn: &Connection, encrypted: bool, passphrase: Option<&str>) -> anyhow::Result<()> {
+pub(crate) fn set_pragmas(
+    conn: &Connection,
+    encrypted: bool,
+    passphrase: Option<&str>,
+    cipher: &CipherParams,
+) -> anyhow::Result<()> {
     conn.pragma_update(None, "foreign_keys", "ON")?;
 
     if encrypted {
         let key = passphrase.context("passphrase required for encrypted database")?;
         // SQLCipher key (works with bundled-sqlcipher)
         conn.pragma_update(None, "key", key)?;
-
-        // Reasonable SQLCipher defaults
-        // https://www.zetetic.net/sqlcipher/sqlcipher-api/
-        conn.execute_batch(
-            "PRAGMA cipher_page_size = 4096;\
-             PRAGMA kdf_iter = 64000;\
-             PRAGMA cipher_hmac_algorithm = HMAC_SHA512;\
-             PRAGMA cipher_kdf_algorithm = PBKDF2_HMAC_SHA512;",
-        )?;
+        set_cipher_settings(conn, "main", cipher)?;
 
         // Force a read to validate key
         let _: i64 = conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get(0))?;
@@ -124,8 +254,150 @@ fn set_pragmas(conn: &Connection, encrypted: bool, passphrase: Option<&str>) ->
     Ok(())
 }
 
-fn migrate_to_v1(conn: &Connection) -> anyhow::Result<()> {
-    // Meta table
+// Must be applied after the key and before the first read of `schema`.
+// https://www.zetetic.net/sqlcipher/sqlcipher-api/
+fn set_cipher_settings(conn: &Connection, schema: &str, cipher: &CipherParams) -> anyhow::Result<()> {
+    // Values are spliced into the SQL, so only ever use validated ones.
+    cipher.validate()?;
+    conn.execute_batch(&format!(
+        "PRAGMA {schema}.cipher_page_size = {};\
+         PRAGMA {schema}.kdf_iter = {};\
+         PRAGMA {schema}.cipher_hmac_algorithm = {};\
+         PRAGMA {schema}.cipher_kdf_algorithm = {};",
+        cipher.page_size, cipher.kdf_iter, cipher.hmac_algorithm, cipher.kdf_algorithm
+    ))?;
+    Ok(())
+}
+
+/// Write a full copy of the open database to a new file at `dest`, encrypted
+/// with `passphrase` and `cipher` or plaintext when `passphrase` is `None`.
+pub(crate) fn export_copy(
+    conn: &Connection,
+    dest: &Path,
+    passphrase: Option<&str>,
+    cipher: &CipherParams,
+) -> anyhow::Result<()> {
+    if dest.exists() {
+        anyhow::bail!("{} already exists", dest.display());
+    }
+    // Fold the WAL into the main file so the export sees every committed write.
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS export KEY ?2",
+        params![dest.to_string_lossy(), passphrase.unwrap_or("")],
+    )
+    .with_context(|| format!("create {}", dest.display()))?;
+
+    let exported = (|| -> anyhow::Result<()> {
+        if passphrase.is_some() {
+            set_cipher_settings(conn, "export", cipher)?;
+        }
+        conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()))?;
+        Ok(())
+    })();
+    conn.execute_batch("DETACH DATABASE export")?;
+    exported
+}
+
+/// Open `path` on a throwaway connection to check that the key works.
+pub(crate) fn verify_key(
+    path: &Path,
+    encrypted: bool,
+    passphrase: Option<&str>,
+    cipher: &CipherParams,
+) -> anyhow::Result<()> {
+    let conn = Connection::open(path).with_context(|| format!("open db at {}", path.display()))?;
+    set_pragmas(&conn, encrypted, passphrase, cipher)?;
+    conn.query_row("SELECT count(*) FROM meta", [], |_| Ok(()))
+        .context("db probe query")?;
+    Ok(())
+}
+
+/// Highest schema version this build understands.
+pub const SCHEMA_VERSION: i64 = 14;
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    up: fn(&Connection) -> anyhow::Result<()>,
+}
+
+// Ordered, append-only. Never edit a migration that has shipped; add a new one instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial schema",
+        up: migrate_v1,
+    },
+    Migration {
+        version: 2,
+        name: "instrument catalog",
+        up: migrate_v2,
+    },
+    Migration {
+        version: 3,
+        name: "trade prices and computed pnl",
+        up: migrate_v3,
+    },
+    Migration {
+        version: 4,
+        name: "executions",
+        up: migrate_v4,
+    },
+    Migration {
+        version: 5,
+        name: "execution dedupe key",
+        up: migrate_v5,
+    },
+    Migration {
+        version: 6,
+        name: "execution realized pnl",
+        up: migrate_v6,
+    },
+    Migration {
+        version: 7,
+        name: "import profiles",
+        up: migrate_v7,
+    },
+    Migration {
+        version: 8,
+        name: "import batches and trade fingerprints",
+        up: migrate_v8,
+    },
+    Migration {
+        version: 9,
+        name: "accounts",
+        up: migrate_v9,
+    },
+    Migration {
+        version: 10,
+        name: "prop firm rules",
+        up: migrate_v10,
+    },
+    Migration {
+        version: 11,
+        name: "full-text search",
+        up: migrate_v11,
+    },
+    Migration {
+        version: 12,
+        name: "tags",
+        up: migrate_v12,
+    },
+    Migration {
+        version: 13,
+        name: "attachments",
+        up: migrate_v13,
+    },
+    Migration {
+        version: 14,
+        name: "execution fingerprints",
+        up: migrate_v14,
+    },
+];
+
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS meta (
             schema_version INTEGER NOT NULL,
@@ -133,16 +405,45 @@ fn migrate_to_v1(conn: &Connection) -> anyhow::Result<()> {
         );",
     )?;
 
-    // If meta empty, insert.
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM meta", [], |row| row.get(0))?;
-    if count == 0 {
+    let current = schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        anyhow::bail!(
+            "database schema v{current} is newer than this app supports (v{SCHEMA_VERSION}); please update FTJournal"
+        );
+    }
+
+    for m in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        (m.up)(&tx).with_context(|| format!("migration v{} ({})", m.version, m.name))?;
+        set_schema_version(&tx, m.version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+fn schema_version(conn: &Connection) -> anyhow::Result<i64> {
+    let ver: Option<i64> = conn
+        .query_row("SELECT MAX(schema_version) FROM meta", [], |row| row.get(0))
+        .context("read schema version")?;
+    Ok(ver.unwrap_or(0))
+}
+
+fn set_schema_version(conn: &Connection, version: i64) -> anyhow::Result<()> {
+    let updated = conn.execute("UPDATE meta SET schema_version = ?1", params![version])?;
+    if updated == 0 {
         let now = chrono::Utc::now().timestamp_millis();
         conn.execute(
             "INSERT INTO meta (schema_version, created_at_utc) VALUES (?1, ?2)",
-            params![1i64, now],
+            params![version, now],
         )?;
     }
+    Ok(())
+}
 
+// v1 predates the migration runner, so it stays idempotent: databases created by
+// older builds already have these tables and a meta row at version 1.
+fn migrate_v1(conn: &Connection) -> anyhow::Result<()> {
     // Settings
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS settings (
@@ -225,3 +526,387 @@ fn migrate_to_v1(conn: &Connection) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn migrate_v2(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE instruments (
+            root_symbol TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            exchange TEXT NOT NULL,
+            currency TEXT NOT NULL,
+            tick_size REAL NOT NULL,
+            tick_value REAL NOT NULL,
+            point_value REAL NOT NULL
+        );
+
+        ALTER TABLE trades ADD COLUMN instrument TEXT REFERENCES instruments(root_symbol) ON DELETE SET NULL;
+        CREATE INDEX idx_trades_instrument ON trades(instrument);
+        ",
+    )?;
+
+    seed_default_instruments(conn)?;
+
+    // Link existing trades whose free-text symbol matches a catalog entry. The
+    // symbol rules are frozen here as they were at v2, independent of
+    // `instruments::resolve_instrument`.
+    fn roots_v2(symbol: &str) -> Vec<String> {
+        let token = symbol
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .trim_start_matches('/')
+            .to_uppercase();
+        if token.is_empty() {
+            return Vec::new();
+        }
+        let mut out = vec![token.clone()];
+        let without_year = token.trim_end_matches(|c: char| c.is_ascii_digit());
+        if without_year.len() < token.len() && without_year.len() > 1 {
+            if let Some(code) = without_year.chars().last().filter(|c| "FGHJKMNQUVXZ".contains(*c)) {
+                out.push(without_year[..without_year.len() - code.len_utf8()].to_string());
+            }
+        }
+        out
+    }
+
+    let mut stmt = conn.prepare("SELECT id, symbol FROM trades")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (id, symbol) in rows {
+        for root in roots_v2(&symbol) {
+            let linked = conn.execute(
+                "UPDATE trades SET instrument = ?2
+                 WHERE id = ?1 AND EXISTS (SELECT 1 FROM instruments WHERE root_symbol = ?2)",
+                params![id, root],
+            )?;
+            if linked > 0 {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn migrate_v3(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE trades ADD COLUMN entry_price REAL;
+        ALTER TABLE trades ADD COLUMN exit_price REAL;
+        ALTER TABLE trades ADD COLUMN pnl_computed REAL;
+        ALTER TABLE trades ADD COLUMN pnl_mismatch INTEGER NOT NULL DEFAULT 0;
+        ",
+    )?;
+    Ok(())
+}
+
+fn migrate_v4(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE executions (
+            id TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            external_id TEXT NOT NULL,
+            account TEXT NOT NULL,
+            symbol TEXT NOT NULL,
+            instrument TEXT REFERENCES instruments(root_symbol) ON DELETE SET NULL,
+            side TEXT NOT NULL,
+            qty REAL NOT NULL,
+            price REAL NOT NULL,
+            fees REAL NOT NULL,
+            time_utc INTEGER NOT NULL,
+            created_at_utc INTEGER NOT NULL
+        );
+
+        CREATE INDEX idx_executions_account_symbol_time ON executions(account, symbol, time_utc);
+
+        CREATE TABLE trade_executions (
+            trade_id TEXT NOT NULL,
+            execution_id TEXT NOT NULL,
+            qty REAL NOT NULL,
+            PRIMARY KEY (trade_id, execution_id),
+            FOREIGN KEY (trade_id) REFERENCES trades(id) ON DELETE CASCADE,
+            FOREIGN KEY (execution_id) REFERENCES executions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX idx_trade_executions_execution ON trade_executions(execution_id);
+        ",
+    )?;
+    Ok(())
+}
+
+fn migrate_v5(conn: &Connection) -> anyhow::Result<()> {
+    // Earlier imports may already hold repeats; keep the first copy's id and
+    // blank the rest so they stay linked to their trades but fall outside the key.
+    conn.execute_batch(
+        "UPDATE executions SET external_id = ''
+         WHERE external_id != ''
+           AND rowid NOT IN (
+               SELECT MIN(rowid) FROM executions
+               WHERE external_id != ''
+               GROUP BY source, account, external_id
+           );
+
+        CREATE UNIQUE INDEX idx_executions_external
+            ON executions(source, account, external_id)
+            WHERE external_id != '';
+        ",
+    )?;
+    Ok(())
+}
+
+fn migrate_v6(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch("ALTER TABLE executions ADD COLUMN realized_pnl REAL;")?;
+    Ok(())
+}
+
+fn migrate_v7(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE import_profiles (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            columns_json TEXT NOT NULL,
+            datetime_format TEXT NOT NULL,
+            timezone TEXT,
+            decimal_separator TEXT NOT NULL,
+            thousands_separator TEXT NOT NULL,
+            side_map_json TEXT NOT NULL,
+            pnl_includes_fees INTEGER NOT NULL,
+            created_at_utc INTEGER NOT NULL,
+            updated_at_utc INTEGER NOT NULL
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+fn migrate_v8(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE imports (
+            id TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            file_hash TEXT NOT NULL,
+            rows_total INTEGER NOT NULL DEFAULT 0,
+            created INTEGER NOT NULL DEFAULT 0,
+            skipped INTEGER NOT NULL DEFAULT 0,
+            errors INTEGER NOT NULL DEFAULT 0,
+            imported_at_utc INTEGER NOT NULL
+        );
+
+        ALTER TABLE trades ADD COLUMN import_id TEXT REFERENCES imports(id) ON DELETE SET NULL;
+        ALTER TABLE trades ADD COLUMN import_fingerprint TEXT;
+        CREATE UNIQUE INDEX idx_trades_import_fingerprint ON trades(import_fingerprint)
+            WHERE import_fingerprint IS NOT NULL;
+        CREATE INDEX idx_trades_import ON trades(import_id);
+
+        ALTER TABLE executions ADD COLUMN import_id TEXT REFERENCES imports(id) ON DELETE SET NULL;
+        CREATE INDEX idx_executions_import ON executions(import_id);
+        ",
+    )?;
+    Ok(())
+}
+
+fn migrate_v9(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE accounts (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            broker TEXT NOT NULL,
+            account_type TEXT NOT NULL,
+            starting_balance REAL NOT NULL,
+            currency TEXT NOT NULL,
+            created_at_utc INTEGER NOT NULL,
+            updated_at_utc INTEGER NOT NULL
+        );
+
+        ALTER TABLE trades ADD COLUMN account_id TEXT REFERENCES accounts(id) ON DELETE SET NULL;
+        CREATE INDEX idx_trades_account_exit ON trades(account_id, exit_time_utc);
+        ",
+    )?;
+    Ok(())
+}
+
+fn migrate_v10(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE prop_rules (
+            account_id TEXT PRIMARY KEY REFERENCES accounts(id) ON DELETE CASCADE,
+            profit_target REAL,
+            max_drawdown REAL,
+            drawdown_mode TEXT NOT NULL,
+            drawdown_lock_balance REAL,
+            daily_loss_limit REAL,
+            consistency_pct REAL,
+            min_trading_days INTEGER,
+            updated_at_utc INTEGER NOT NULL
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+// The FTS tables store their own copy of the text. External-content tables would
+// key on the base rowid, which VACUUM and sqlcipher_export do not preserve for
+// tables with TEXT primary keys. Instead each id gets a stable FTS rowid in a map
+// table; an UNINDEXED id column would make every update or delete scan the index.
+fn migrate_v11(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE trades_fts_map (
+            trade_id TEXT PRIMARY KEY,
+            fts_rowid INTEGER NOT NULL UNIQUE
+        );
+        CREATE VIRTUAL TABLE trades_fts USING fts5(
+            symbol, notes,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        INSERT INTO trades_fts_map (trade_id, fts_rowid)
+            SELECT id, ROW_NUMBER() OVER (ORDER BY rowid) FROM trades;
+        INSERT INTO trades_fts (rowid, symbol, notes)
+            SELECT m.fts_rowid, t.symbol, t.notes FROM trades t JOIN trades_fts_map m ON m.trade_id = t.id;
+
+        CREATE TRIGGER trades_fts_insert AFTER INSERT ON trades BEGIN
+            INSERT INTO trades_fts_map (trade_id, fts_rowid)
+                VALUES (new.id, (SELECT COALESCE(MAX(fts_rowid), 0) + 1 FROM trades_fts_map));
+            INSERT INTO trades_fts (rowid, symbol, notes)
+                SELECT fts_rowid, new.symbol, new.notes FROM trades_fts_map WHERE trade_id = new.id;
+        END;
+        CREATE TRIGGER trades_fts_update AFTER UPDATE OF symbol, notes ON trades BEGIN
+            DELETE FROM trades_fts WHERE rowid = (SELECT fts_rowid FROM trades_fts_map WHERE trade_id = old.id);
+            INSERT INTO trades_fts (rowid, symbol, notes)
+                SELECT fts_rowid, new.symbol, new.notes FROM trades_fts_map WHERE trade_id = new.id;
+        END;
+        CREATE TRIGGER trades_fts_delete AFTER DELETE ON trades BEGIN
+            DELETE FROM trades_fts WHERE rowid = (SELECT fts_rowid FROM trades_fts_map WHERE trade_id = old.id);
+            DELETE FROM trades_fts_map WHERE trade_id = old.id;
+        END;
+
+        CREATE TABLE journal_fts_map (
+            entry_id TEXT PRIMARY KEY,
+            fts_rowid INTEGER NOT NULL UNIQUE
+        );
+        CREATE VIRTUAL TABLE journal_fts USING fts5(
+            text,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        INSERT INTO journal_fts_map (entry_id, fts_rowid)
+            SELECT id, ROW_NUMBER() OVER (ORDER BY rowid) FROM journal_entries;
+        INSERT INTO journal_fts (rowid, text)
+            SELECT m.fts_rowid, e.text FROM journal_entries e JOIN journal_fts_map m ON m.entry_id = e.id;
+
+        CREATE TRIGGER journal_fts_insert AFTER INSERT ON journal_entries BEGIN
+            INSERT INTO journal_fts_map (entry_id, fts_rowid)
+                VALUES (new.id, (SELECT COALESCE(MAX(fts_rowid), 0) + 1 FROM journal_fts_map));
+            INSERT INTO journal_fts (rowid, text)
+                SELECT fts_rowid, new.text FROM journal_fts_map WHERE entry_id = new.id;
+        END;
+        CREATE TRIGGER journal_fts_update AFTER UPDATE OF text ON journal_entries BEGIN
+            DELETE FROM journal_fts WHERE rowid = (SELECT fts_rowid FROM journal_fts_map WHERE entry_id = old.id);
+            INSERT INTO journal_fts (rowid, text)
+                SELECT fts_rowid, new.text FROM journal_fts_map WHERE entry_id = new.id;
+        END;
+        CREATE TRIGGER journal_fts_delete AFTER DELETE ON journal_entries BEGIN
+            DELETE FROM journal_fts WHERE rowid = (SELECT fts_rowid FROM journal_fts_map WHERE entry_id = old.id);
+            DELETE FROM journal_fts_map WHERE entry_id = old.id;
+        END;
+        ",
+    )?;
+    Ok(())
+}
+
+fn migrate_v12(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE tag_groups (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            sort_order INTEGER NOT NULL
+        );
+        INSERT INTO tag_groups (id, name, sort_order) VALUES
+            ('setup', 'Setup', 1),
+            ('mistake', 'Mistake', 2),
+            ('market_condition', 'Market Condition', 3);
+
+        CREATE TABLE tags (
+            id TEXT PRIMARY KEY,
+            group_id TEXT REFERENCES tag_groups(id) ON DELETE SET NULL,
+            name TEXT NOT NULL COLLATE NOCASE,
+            color TEXT NOT NULL,
+            created_at_utc INTEGER NOT NULL
+        );
+        -- NULLs never compare equal, so UNIQUE (group_id, name) would allow two
+        -- ungrouped tags with the same name.
+        CREATE UNIQUE INDEX idx_tags_group_name ON tags(COALESCE(group_id, ''), name);
+
+        CREATE TABLE trade_tags (
+            trade_id TEXT NOT NULL REFERENCES trades(id) ON DELETE CASCADE,
+            tag_id TEXT NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (trade_id, tag_id)
+        );
+        CREATE INDEX idx_trade_tags_tag ON trade_tags(tag_id);
+        ",
+    )?;
+    Ok(())
+}
+
+// Images live in the database so encryption and backups cover them.
+fn migrate_v13(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE attachments (
+            id TEXT PRIMARY KEY,
+            trade_id TEXT REFERENCES trades(id) ON DELETE CASCADE,
+            journal_entry_id TEXT REFERENCES journal_entries(id) ON DELETE CASCADE,
+            file_name TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            thumbnail BLOB NOT NULL,
+            created_at_utc INTEGER NOT NULL,
+            CHECK ((trade_id IS NULL) <> (journal_entry_id IS NULL))
+        );
+        CREATE INDEX idx_attachments_trade ON attachments(trade_id);
+        CREATE INDEX idx_attachments_journal_entry ON attachments(journal_entry_id);
+        ",
+    )?;
+    Ok(())
+}
+
+// Fills without a broker id escape idx_executions_external; imported ones are
+// matched on their contents instead.
+fn migrate_v14(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE executions ADD COLUMN import_fingerprint TEXT;
+        CREATE INDEX idx_executions_import_fingerprint ON executions(import_fingerprint)
+            WHERE import_fingerprint IS NOT NULL;
+        ",
+    )?;
+
+    // Fingerprint key frozen as it was at v14, independent of
+    // `imports::execution_fingerprint`. Stored sides are already "buy"/"sell".
+    let mut stmt = conn.prepare(
+        "SELECT id, source, account, symbol, side, qty, price, time_utc
+         FROM executions WHERE import_id IS NOT NULL AND external_id = ''",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            let key = format!(
+                "{}|{}|{}|{}|{}|{}|{}",
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?.trim(),
+                row.get::<_, String>(3)?.trim().to_uppercase(),
+                row.get::<_, String>(4)?,
+                row.get::<_, f64>(5)?,
+                row.get::<_, f64>(6)?,
+                row.get::<_, i64>(7)?,
+            );
+            Ok((row.get::<_, String>(0)?, key))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (id, key) in rows {
+        conn.execute(
+            "UPDATE executions SET import_fingerprint = ?2 WHERE id = ?1",
+            params![id, format!("{:x}", sha2::Sha256::digest(key.as_bytes()))],
+        )?;
+    }
+
+    Ok(())
+}
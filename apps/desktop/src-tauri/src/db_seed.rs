@@ -26,3 +26,37 @@ pub fn seed_default_rules(conn: &Connection) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+pub fn seed_default_instruments(conn: &Connection) -> anyhow::Result<()> {
+    // (root, name, exchange, tick size, tick value, point value); all USD.
+    let defaults: Vec<(&str, &str, &str, f64, f64, f64)> = vec![
+        ("ES", "E-mini S&P 500", "CME", 0.25, 12.50, 50.0),
+        ("MES", "Micro E-mini S&P 500", "CME", 0.25, 1.25, 5.0),
+        ("NQ", "E-mini Nasdaq-100", "CME", 0.25, 5.00, 20.0),
+        ("MNQ", "Micro E-mini Nasdaq-100", "CME", 0.25, 0.50, 2.0),
+        ("RTY", "E-mini Russell 2000", "CME", 0.10, 5.00, 50.0),
+        ("M2K", "Micro E-mini Russell 2000", "CME", 0.10, 0.50, 5.0),
+        ("6E", "Euro FX", "CME", 0.00005, 6.25, 125000.0),
+        ("YM", "E-mini Dow ($5)", "CBOT", 1.0, 5.00, 5.0),
+        ("MYM", "Micro E-mini Dow", "CBOT", 1.0, 0.50, 0.5),
+        ("ZB", "U.S. Treasury Bond", "CBOT", 0.03125, 31.25, 1000.0),
+        ("ZN", "10-Year U.S. Treasury Note", "CBOT", 0.015625, 15.625, 1000.0),
+        ("CL", "Crude Oil", "NYMEX", 0.01, 10.00, 1000.0),
+        ("MCL", "Micro WTI Crude Oil", "NYMEX", 0.01, 1.00, 100.0),
+        ("NG", "Henry Hub Natural Gas", "NYMEX", 0.001, 10.00, 10000.0),
+        ("GC", "Gold", "COMEX", 0.10, 10.00, 100.0),
+        ("MGC", "Micro Gold", "COMEX", 0.10, 1.00, 10.0),
+        ("SI", "Silver", "COMEX", 0.005, 25.00, 5000.0),
+    ];
+
+    for (root, name, exchange, tick_size, tick_value, point_value) in defaults {
+        conn.execute(
+            "INSERT OR IGNORE INTO instruments
+                (root_symbol, name, exchange, currency, tick_size, tick_value, point_value)
+             VALUES (?1, ?2, ?3, 'USD', ?4, ?5, ?6)",
+            params![root, name, exchange, tick_size, tick_value, point_value],
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,316 @@
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::matcher::{match_fills, Fill, MatchMethod, RoundTrip};
+use crate::models::{Execution, ExecutionInput, LinkedExecution, TradeInput};
+
+#[derive(Debug, serde::Serialize)]
+pub struct BuildTradesResult {
+    pub created: i64,
+    pub errors: Vec<String>,
+}
+
+/// What `insert_execution` did with a fill.
+#[derive(Debug, PartialEq)]
+pub enum StoredExecution {
+    Inserted(String),
+    /// A broker re-exported an order that filled further; the stored, still
+    /// unmatched fill took the new quantity and price.
+    Updated(String),
+    /// Already stored with the same quantity and price.
+    Duplicate,
+}
+
+const EXECUTION_COLUMNS: &str =
+    "e.id, e.source, e.external_id, e.account, e.symbol, e.instrument, e.side, e.qty, e.price, e.fees, e.realized_pnl, e.time_utc, e.created_at_utc";
+
+fn execution_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Execution> {
+    Ok(Execution {
+        id: row.get(0)?,
+        source: row.get(1)?,
+        external_id: row.get(2)?,
+        account: row.get(3)?,
+        symbol: row.get(4)?,
+        instrument: row.get(5)?,
+        side: row.get(6)?,
+        qty: row.get(7)?,
+        price: row.get(8)?,
+        fees: row.get(9)?,
+        realized_pnl: row.get(10)?,
+        time_utc: row.get(11)?,
+        created_at_utc: row.get(12)?,
+    })
+}
+
+// Quantity of each fill not yet allocated to a trade.
+const UNMATCHED_QTY: &str =
+    "e.qty - COALESCE((SELECT SUM(te.qty) FROM trade_executions te WHERE te.execution_id = e.id), 0)";
+
+pub fn list_executions(conn: &Connection, limit: i64, offset: i64, unmatched_only: bool) -> anyhow::Result<Vec<Execution>> {
+    let filter = if unmatched_only {
+        format!("WHERE {UNMATCHED_QTY} > 1e-9")
+    } else {
+        String::new()
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {EXECUTION_COLUMNS}
+         FROM executions e
+         {filter}
+         ORDER BY e.time_utc DESC
+         LIMIT ?1 OFFSET ?2"
+    ))?;
+    let rows = stmt.query_map(params![limit, offset], execution_from_row)?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+pub fn executions_for_trade(conn: &Connection, trade_id: &str) -> anyhow::Result<Vec<LinkedExecution>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {EXECUTION_COLUMNS}, te.qty
+         FROM trade_executions te
+         JOIN executions e ON e.id = te.execution_id
+         WHERE te.trade_id = ?1
+         ORDER BY e.time_utc ASC, e.rowid ASC"
+    ))?;
+    let rows = stmt.query_map(params![trade_id], |row| {
+        Ok(LinkedExecution {
+            execution: execution_from_row(row)?,
+            qty: row.get(13)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+/// Store a fill. A fill with the same source, account and broker id that was
+/// stored before is a duplicate if nothing changed, is updated if it changed
+/// but is not yet part of a trade, and is an error otherwise.
+pub fn insert_execution(conn: &Connection, input: &ExecutionInput) -> anyhow::Result<StoredExecution> {
+    validate_execution(input)?;
+
+    let id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    let instrument = crate::instruments::resolve_instrument(conn, &input.symbol)?;
+
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO executions (
+            id, source, external_id, account, symbol, instrument, side, qty, price, fees, realized_pnl, time_utc, created_at_utc
+        ) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13)",
+        params![
+            id,
+            input.source,
+            input.external_id.trim(),
+            input.account.trim(),
+            input.symbol.trim(),
+            instrument.map(|i| i.root_symbol),
+            normalize_side(&input.side)?,
+            input.qty,
+            input.price,
+            input.fees,
+            input.realized_pnl,
+            input.time_utc,
+            now
+        ],
+    )?;
+
+    if inserted > 0 {
+        return Ok(StoredExecution::Inserted(id));
+    }
+    update_reexported(conn, input)
+}
+
+// The insert hit idx_executions_external, so a fill with this broker id exists.
+fn update_reexported(conn: &Connection, input: &ExecutionInput) -> anyhow::Result<StoredExecution> {
+    let (id, qty, price, matched): (String, f64, f64, f64) = conn.query_row(
+        "SELECT e.id, e.qty, e.price,
+                COALESCE((SELECT SUM(te.qty) FROM trade_executions te WHERE te.execution_id = e.id), 0)
+         FROM executions e
+         WHERE e.source = ?1 AND e.account = ?2 AND e.external_id = ?3",
+        params![input.source, input.account.trim(), input.external_id.trim()],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    )?;
+    if (qty - input.qty).abs() <= 1e-9 && (price - input.price).abs() <= 1e-9 {
+        return Ok(StoredExecution::Duplicate);
+    }
+    if matched > 1e-9 {
+        anyhow::bail!(
+            "fill {} changed (qty {qty} @ {price} -> {} @ {}) after it was matched into a trade; undo that import first",
+            input.external_id.trim(),
+            input.qty,
+            input.price
+        );
+    }
+    conn.execute(
+        "UPDATE executions SET qty=?2, price=?3, fees=?4, realized_pnl=?5, time_utc=?6 WHERE id=?1",
+        params![id, input.qty, input.price, input.fees, input.realized_pnl, input.time_utc],
+    )?;
+    Ok(StoredExecution::Updated(id))
+}
+
+pub fn delete_execution(conn: &Connection, id: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM executions WHERE id=?1", params![id])?;
+    Ok(())
+}
+
+/// Turn every fill not yet allocated to a trade into round-trip trades.
+///
+/// Fills are grouped per account + contract. Positions still open at the end
+/// stay unmatched and are picked up again by the next run. With `import_id`
+/// set, only the account + contract pairs that import brought fills for are
+/// matched, so older problem fills elsewhere don't surface as its errors.
+pub fn build_trades(
+    conn: &Connection,
+    tz: &str,
+    method: MatchMethod,
+    import_id: Option<&str>,
+) -> anyhow::Result<BuildTradesResult> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.id, e.account, e.symbol, e.side, {UNMATCHED_QTY} AS open_qty, e.price, e.fees, e.qty, e.time_utc, e.realized_pnl
+         FROM executions e
+         WHERE {UNMATCHED_QTY} > 1e-9
+           AND (?1 IS NULL OR EXISTS (
+               SELECT 1 FROM executions s WHERE s.import_id = ?1 AND s.account = e.account AND s.symbol = e.symbol
+           ))
+         ORDER BY e.account, e.symbol, e.time_utc ASC, e.rowid ASC"
+    ))?;
+    let rows = stmt.query_map(params![import_id], |row| {
+        let account: String = row.get(1)?;
+        let symbol: String = row.get(2)?;
+        let side: String = row.get(3)?;
+        let open_qty: f64 = row.get(4)?;
+        let fees: f64 = row.get(6)?;
+        let full_qty: f64 = row.get(7)?;
+        let realized_pnl: Option<f64> = row.get(9)?;
+        Ok((
+            (account, symbol),
+            Fill {
+                execution_id: row.get(0)?,
+                is_buy: side == "buy",
+                qty: open_qty,
+                price: row.get(5)?,
+                // Only the unmatched share of the commission is still unaccounted for.
+                fees: fees * open_qty / full_qty,
+                time_utc: row.get(8)?,
+                // A fill that was partly allocated already has had its realized PnL used.
+                realized_pnl: realized_pnl.filter(|_| (full_qty - open_qty).abs() <= 1e-9),
+            },
+        ))
+    })?;
+
+    let mut groups: Vec<((String, String), Vec<Fill>)> = Vec::new();
+    for r in rows {
+        let (key, fill) = r?;
+        match groups.last_mut() {
+            Some((k, fills)) if *k == key => fills.push(fill),
+            _ => groups.push((key, vec![fill])),
+        }
+    }
+
+    let mut created = 0i64;
+    let mut errors = Vec::new();
+    for ((account, symbol), fills) in groups {
+        if crate::instruments::resolve_instrument(conn, &symbol)?.is_none() {
+            errors.push(format!("{symbol}: unknown instrument; add it to the instrument catalog first"));
+            continue;
+        }
+        let account_id = crate::accounts::account_for_label(conn, &account, "")?;
+        // The trips of a group were matched in one pass, each against the lots
+        // the earlier ones left; so they are stored all together or not at all.
+        let trips = match_fills(&fills, method);
+        let stored = crate::db::with_savepoint(conn, || {
+            for trip in &trips {
+                create_round_trip(conn, &symbol, account_id.as_deref(), tz, trip)?;
+            }
+            Ok(())
+        });
+        match stored {
+            Ok(()) => created += trips.len() as i64,
+            Err(e) => {
+                let label = if account.is_empty() { symbol.clone() } else { format!("{account} {symbol}") };
+                errors.push(format!("{label}: failed to create trades: {e}"));
+            }
+        }
+    }
+
+    Ok(BuildTradesResult { created, errors })
+}
+
+fn create_round_trip(
+    conn: &Connection,
+    symbol: &str,
+    account_id: Option<&str>,
+    tz: &str,
+    trip: &RoundTrip,
+) -> anyhow::Result<()> {
+    let mut input = round_trip_to_trade_input(symbol, tz, trip);
+    input.account_id = account_id.map(str::to_string);
+    let trade = crate::trades::create_trade(conn, input)?;
+    for (execution_id, qty) in &trip.legs {
+        conn.execute(
+            "INSERT INTO trade_executions (trade_id, execution_id, qty) VALUES (?1, ?2, ?3)",
+            params![trade.id, execution_id, qty],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn round_trip_to_trade_input(symbol: &str, tz: &str, trip: &RoundTrip) -> TradeInput {
+    TradeInput {
+        market: "futures".to_string(),
+        symbol: symbol.to_string(),
+        side: trip.side.to_string(),
+        qty: trip.qty,
+        entry_time_utc: trip.entry_time_utc,
+        exit_time_utc: trip.exit_time_utc,
+        timezone: tz.to_string(),
+        session: "other".to_string(),
+        entry_price: Some(trip.entry_price),
+        exit_price: Some(trip.exit_price),
+        // Broker-reported PnL, when present, wins over the price-based computation.
+        pnl_amount: trip.realized_pnl,
+        pnl_includes_fees: true,
+        fees: trip.fees,
+        notes: String::new(),
+        instrument: None,
+        account_id: None,
+        tags: None,
+        rules_checked: None,
+    }
+}
+
+/// Map broker side vocabularies onto "buy" / "sell".
+pub fn normalize_side(side: &str) -> anyhow::Result<&'static str> {
+    match side.trim().to_ascii_lowercase().as_str() {
+        "buy" | "b" | "bot" | "bought" | "buy to cover" | "buytocover" | "long" => Ok("buy"),
+        "sell" | "s" | "sld" | "sold" | "sell short" | "sellshort" | "short" => Ok("sell"),
+        other => anyhow::bail!("unsupported execution side: {other}"),
+    }
+}
+
+fn validate_execution(input: &ExecutionInput) -> anyhow::Result<()> {
+    if input.symbol.trim().is_empty() {
+        anyhow::bail!("symbol is required");
+    }
+    // Written so NaN fails: every comparison with NaN is false.
+    if !(input.qty.is_finite() && input.qty > 0.0) {
+        anyhow::bail!("qty must be > 0");
+    }
+    if !input.price.is_finite() {
+        anyhow::bail!("price must be a finite number");
+    }
+    if !(input.fees.is_finite() && input.fees >= 0.0) {
+        anyhow::bail!("fees must be >= 0");
+    }
+    if input.realized_pnl.is_some_and(|p| !p.is_finite()) {
+        anyhow::bail!("realized_pnl must be a finite number");
+    }
+    Ok(())
+}
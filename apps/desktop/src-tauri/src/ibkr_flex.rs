@@ -0,0 +1,160 @@
+use anyhow::Context;
+use chrono_tz::Tz;
+use quick_xml::events::{BytesStart, Event};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::csv_import::{parse_local_with_formats, run_import, CsvImportResult, ImportMode, ImportRecord, ParsedLine};
+use crate::matcher::MatchMethod;
+use crate::models::{ExecutionInput, Instrument};
+
+const SOURCE: &str = "ibkr";
+
+// Flex "dateTime" depends on the query's date/time settings.
+const TIME_FORMATS: &[&str] = &[
+    "%Y%m%d;%H%M%S",
+    "%Y%m%d;%H:%M:%S",
+    "%Y-%m-%d;%H:%M:%S",
+    "%Y%m%d %H%M%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d, %H:%M:%S",
+];
+
+/// Import futures executions from an Interactive Brokers Flex Query XML report.
+///
+/// Only `<Trade>` elements with assetCategory="FUT" at execution level are used.
+/// Fills go through the normal executions pipeline; IBKR's fifoPnlRealized is
+/// kept on closing fills so FIFO trades carry the broker's net PnL.
+pub fn import_ibkr_flex(
+    conn: &Connection,
+    xml_path: &Path,
+    tz_name: &str,
+    method: MatchMethod,
+    mode: ImportMode,
+) -> anyhow::Result<CsvImportResult> {
+    let fallback_tz: Tz = tz_name.parse().context("invalid timezone")?;
+    let xml = std::fs::read_to_string(xml_path).with_context(|| format!("open flex report {}", xml_path.display()))?;
+
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut lines: Vec<ParsedLine> = Vec::new();
+    let mut saw_statement = false;
+    // Line of the current event, counted incrementally from the previous position.
+    let (mut line, mut counted) = (1, 0);
+    loop {
+        let pos = reader.buffer_position() as usize;
+        line += xml[counted..pos].matches('\n').count();
+        counted = pos;
+        match reader.read_event().context("invalid flex XML")? {
+            Event::Start(e) | Event::Empty(e) => match e.name().as_ref() {
+                b"FlexStatement" => saw_statement = true,
+                b"Trade" => {
+                    let parsed = attributes(&e).and_then(|attrs| trade_row(&attrs, &fallback_tz));
+                    let record = parsed.map(|r| {
+                        r.map(|(input, instrument)| ImportRecord::ExecutionWithInstrument {
+                            input,
+                            instrument: Box::new(instrument),
+                        })
+                    });
+                    lines.push((line, record));
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !saw_statement {
+        anyhow::bail!("not an IBKR Flex Query report (no FlexStatement element)");
+    }
+
+    run_import(conn, SOURCE, xml_path, lines, tz_name, method, mode)
+}
+
+fn attributes(e: &BytesStart<'_>) -> anyhow::Result<HashMap<String, String>> {
+    let mut out = HashMap::new();
+    for attr in e.attributes() {
+        let attr = attr?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        out.insert(key, attr.unescape_value()?.trim().to_string());
+    }
+    Ok(out)
+}
+
+/// The fill and a catalog entry for its contract, used if the root is not known yet.
+fn trade_row(attrs: &HashMap<String, String>, fallback_tz: &Tz) -> anyhow::Result<Option<(ExecutionInput, Instrument)>> {
+    let get = |k: &str| attrs.get(k).map(String::as_str).filter(|v| !v.is_empty());
+    let req = |k: &str| get(k).with_context(|| format!("missing attribute {k}"));
+    let num = |k: &str| -> anyhow::Result<f64> {
+        req(k)?.parse::<f64>().with_context(|| format!("invalid {k}: {}", attrs[k]))
+    };
+
+    if get("assetCategory") != Some("FUT") {
+        return Ok(None);
+    }
+    // ORDER and CLOSED_LOT rows summarise the EXECUTION rows; counting them would double up.
+    if get("levelOfDetail").is_some_and(|l| !l.eq_ignore_ascii_case("EXECUTION")) {
+        return Ok(None);
+    }
+    let side = req("buySell")?;
+    // Busted trades appear as "BUY (Ca.)" / "SELL (Ca.)" next to the original.
+    if side.contains('(') {
+        return Ok(None);
+    }
+
+    let symbol = req("symbol")?.to_uppercase();
+    let instrument = placeholder_instrument(attrs, &symbol, num("multiplier")?);
+
+    let exchange = get("listingExchange").or(get("exchange")).unwrap_or_default();
+    let tz = exchange_tz(exchange).unwrap_or(*fallback_tz);
+
+    // fifoPnlRealized is net of commissions and only meaningful on closing fills.
+    let closing = get("openCloseIndicator").is_some_and(|oc| oc.contains('C'));
+    let realized_pnl = if closing { get("fifoPnlRealized").map(str::parse::<f64>).transpose()? } else { None };
+
+    let input = ExecutionInput {
+        source: SOURCE.to_string(),
+        external_id: get("ibExecID").or(get("tradeID")).unwrap_or_default().to_string(),
+        account: get("accountId").unwrap_or_default().to_string(),
+        symbol,
+        side: side.to_string(),
+        qty: num("quantity")?.abs(),
+        price: num("tradePrice")?,
+        fees: get("ibCommission").map(str::parse::<f64>).transpose()?.unwrap_or(0.0).abs(),
+        realized_pnl,
+        time_utc: parse_local_with_formats(&tz, req("dateTime")?, TIME_FORMATS)?,
+    };
+    Ok(Some((input, instrument)))
+}
+
+/// Catalog entry for the contract's root, using the report's multiplier as point value.
+fn placeholder_instrument(attrs: &HashMap<String, String>, symbol: &str, multiplier: f64) -> Instrument {
+    let get = |k: &str| attrs.get(k).cloned().unwrap_or_default();
+    let root = attrs.get("underlyingSymbol").filter(|s| !s.is_empty()).map_or(symbol, String::as_str);
+    // Flex reports carry no tick size; 0.01 is a placeholder to correct in the catalog.
+    let tick_size = 0.01;
+    Instrument {
+        root_symbol: root.to_string(),
+        name: get("description"),
+        exchange: get("listingExchange"),
+        currency: get("currency"),
+        tick_size,
+        tick_value: tick_size * multiplier,
+        point_value: multiplier,
+    }
+}
+
+/// Flex reports wall-clock times in the exchange's local time.
+fn exchange_tz(exchange: &str) -> Option<Tz> {
+    let tz = match exchange.to_ascii_uppercase().as_str() {
+        "CME" | "CBOT" | "NYMEX" | "COMEX" | "GLOBEX" | "ECBOT" | "CFE" | "CBOE" => chrono_tz::America::Chicago,
+        "NYBOT" | "ICEUS" | "NYSELIFFE" => chrono_tz::America::New_York,
+        "EUREX" => chrono_tz::Europe::Berlin,
+        "ICEEU" | "IPE" | "LIFFE" => chrono_tz::Europe::London,
+        "OSE.JPN" => chrono_tz::Asia::Tokyo,
+        "SGX" => chrono_tz::Asia::Singapore,
+        "HKFE" => chrono_tz::Asia::Hong_Kong,
+        _ => return None,
+    };
+    Some(tz)
+}
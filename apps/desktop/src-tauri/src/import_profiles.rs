@@ -0,0 +1,327 @@
+use anyhow::Context;
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::csv_import::{parse_local_with_formats, Columns};
+use crate::models::{ImportProfile, ImportProfileInput, TradeInput};
+
+/// Trade fields a source column can be mapped to.
+const FIELDS: &[&str] = &[
+    "symbol",
+    "side",
+    "qty",
+    "entry_time",
+    "exit_time",
+    "entry_price",
+    "exit_price",
+    "pnl",
+    "fees",
+    "notes",
+    "account",
+    "session",
+    "market",
+    "instrument",
+];
+const REQUIRED_FIELDS: &[&str] = &["symbol", "side", "qty", "entry_time", "exit_time"];
+
+const PROFILE_COLUMNS: &str = "id, name, columns_json, datetime_format, timezone, decimal_separator, thousands_separator,
+     side_map_json, pnl_includes_fees, created_at_utc, updated_at_utc";
+
+fn profile_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<(ImportProfile, String, String)> {
+    let profile = ImportProfile {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        columns: HashMap::new(),
+        datetime_format: row.get(3)?,
+        timezone: row.get(4)?,
+        decimal_separator: row.get(5)?,
+        thousands_separator: row.get(6)?,
+        side_map: HashMap::new(),
+        pnl_includes_fees: row.get::<_, i64>(8)? != 0,
+        created_at_utc: row.get(9)?,
+        updated_at_utc: row.get(10)?,
+    };
+    Ok((profile, row.get(2)?, row.get(7)?))
+}
+
+fn decode_maps((mut profile, columns, side_map): (ImportProfile, String, String)) -> anyhow::Result<ImportProfile> {
+    profile.columns = serde_json::from_str(&columns).context("parse profile columns json")?;
+    profile.side_map = serde_json::from_str(&side_map).context("parse profile side map json")?;
+    Ok(profile)
+}
+
+pub fn list_profiles(conn: &Connection) -> anyhow::Result<Vec<ImportProfile>> {
+    let mut stmt = conn.prepare(&format!("SELECT {PROFILE_COLUMNS} FROM import_profiles ORDER BY name ASC"))?;
+    let rows = stmt.query_map([], profile_from_row)?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(decode_maps(r?)?);
+    }
+    Ok(out)
+}
+
+pub fn get_profile(conn: &Connection, id: &str) -> anyhow::Result<ImportProfile> {
+    let row = conn
+        .query_row(
+            &format!("SELECT {PROFILE_COLUMNS} FROM import_profiles WHERE id = ?1"),
+            params![id],
+            profile_from_row,
+        )
+        .optional()?
+        .context("import profile not found")?;
+    decode_maps(row)
+}
+
+pub fn create_profile(conn: &Connection, input: ImportProfileInput) -> anyhow::Result<ImportProfile> {
+    validate_profile(&input)?;
+
+    let id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT INTO import_profiles (
+            id, name, columns_json, datetime_format, timezone, decimal_separator, thousands_separator,
+            side_map_json, pnl_includes_fees, created_at_utc, updated_at_utc
+        ) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11)",
+        params![
+            id,
+            input.name.trim(),
+            serde_json::to_string(&input.columns)?,
+            input.datetime_format,
+            input.timezone,
+            input.decimal_separator,
+            input.thousands_separator,
+            serde_json::to_string(&input.side_map)?,
+            if input.pnl_includes_fees { 1 } else { 0 },
+            now,
+            now
+        ],
+    )?;
+
+    get_profile(conn, &id)
+}
+
+pub fn update_profile(conn: &Connection, id: &str, input: ImportProfileInput) -> anyhow::Result<ImportProfile> {
+    validate_profile(&input)?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let n = conn.execute(
+        "UPDATE import_profiles SET
+            name=?2, columns_json=?3, datetime_format=?4, timezone=?5, decimal_separator=?6,
+            thousands_separator=?7, side_map_json=?8, pnl_includes_fees=?9, updated_at_utc=?10
+         WHERE id=?1",
+        params![
+            id,
+            input.name.trim(),
+            serde_json::to_string(&input.columns)?,
+            input.datetime_format,
+            input.timezone,
+            input.decimal_separator,
+            input.thousands_separator,
+            serde_json::to_string(&input.side_map)?,
+            if input.pnl_includes_fees { 1 } else { 0 },
+            now
+        ],
+    )?;
+    if n == 0 {
+        anyhow::bail!("import profile not found");
+    }
+
+    get_profile(conn, id)
+}
+
+pub fn delete_profile(conn: &Connection, id: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM import_profiles WHERE id=?1", params![id])?;
+    Ok(())
+}
+
+fn validate_profile(input: &ImportProfileInput) -> anyhow::Result<()> {
+    if input.name.trim().is_empty() {
+        anyhow::bail!("profile name is required");
+    }
+    let mut mapped = std::collections::HashSet::new();
+    for field in input.columns.values() {
+        if !FIELDS.contains(&field.as_str()) {
+            anyhow::bail!("unknown field: {field}");
+        }
+        if !mapped.insert(field.as_str()) {
+            anyhow::bail!("more than one column is mapped to {field}");
+        }
+    }
+    for field in REQUIRED_FIELDS {
+        if !input.columns.values().any(|f| f == field) {
+            anyhow::bail!("no column mapped to {field}");
+        }
+    }
+    if input.decimal_separator.chars().count() != 1 {
+        anyhow::bail!("decimal separator must be a single character");
+    }
+    if input.thousands_separator.chars().count() > 1 {
+        anyhow::bail!("thousands separator must be empty or a single character");
+    }
+    if input.decimal_separator == input.thousands_separator {
+        anyhow::bail!("decimal and thousands separators must differ");
+    }
+    if let Some(tz) = input.timezone.as_deref().filter(|s| !s.is_empty()) {
+        tz.parse::<Tz>().map_err(|_| anyhow::anyhow!("invalid timezone: {tz}"))?;
+    }
+    for side in input.side_map.values() {
+        if side != "long" && side != "short" {
+            anyhow::bail!("side values must map to long or short, got {side}");
+        }
+    }
+    Ok(())
+}
+
+/// Per-file state for parsing rows with a profile: field -> source header.
+pub(crate) struct ProfileReader<'p> {
+    profile: &'p ImportProfile,
+    cols: Columns,
+    fields: HashMap<&'p str, &'p str>,
+    tz: Tz,
+}
+
+impl<'p> ProfileReader<'p> {
+    pub(crate) fn new(profile: &'p ImportProfile, headers: &csv::StringRecord, tz: Tz) -> anyhow::Result<Self> {
+        let cols = Columns::new(headers);
+        let mut fields = HashMap::new();
+        for (header, field) in &profile.columns {
+            if !cols.has(header) {
+                anyhow::bail!("column \"{header}\" from profile \"{}\" is not in this file", profile.name);
+            }
+            if fields.insert(field.as_str(), header.as_str()).is_some() {
+                anyhow::bail!("profile \"{}\" maps more than one column to {field}", profile.name);
+            }
+        }
+        Ok(Self { profile, cols, fields, tz })
+    }
+
+    fn get<'r>(&self, rec: &'r csv::StringRecord, field: &str) -> Option<&'r str> {
+        self.fields.get(field).and_then(|h| self.cols.get(rec, &[h]))
+    }
+
+    fn req<'r>(&self, rec: &'r csv::StringRecord, field: &str) -> anyhow::Result<&'r str> {
+        self.get(rec, field).with_context(|| format!("missing value for {field}"))
+    }
+
+    fn number(&self, rec: &csv::StringRecord, field: &str) -> anyhow::Result<Option<f64>> {
+        self.get(rec, field)
+            .map(|s| parse_number(s, &self.profile.decimal_separator, &self.profile.thousands_separator))
+            .transpose()
+            .with_context(|| format!("invalid {field}"))
+    }
+
+    fn time(&self, rec: &csv::StringRecord, field: &str) -> anyhow::Result<i64> {
+        let s = self.req(rec, field)?;
+        let fmt = self.profile.datetime_format.trim();
+        let formats: &[&str] = if fmt.is_empty() { &[] } else { &[fmt] };
+        parse_local_with_formats(&self.tz, s, formats).with_context(|| format!("invalid {field}"))
+    }
+
+    fn side(&self, rec: &csv::StringRecord) -> anyhow::Result<String> {
+        let raw = self.req(rec, "side")?;
+        let mapped = self
+            .profile
+            .side_map
+            .iter()
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case(raw))
+            .map(|(_, v)| v.as_str());
+        if let Some(side) = mapped {
+            return Ok(side.to_string());
+        }
+        match raw.to_ascii_lowercase().as_str() {
+            "long" | "buy" => Ok("long".to_string()),
+            "short" | "sell" => Ok("short".to_string()),
+            _ => anyhow::bail!("side \"{raw}\" is not in the profile's side vocabulary"),
+        }
+    }
+
+    /// The row's trade and account label; `Ok(None)` for rows without a symbol
+    /// (blank or summary lines).
+    pub(crate) fn trade_input(&self, rec: &csv::StringRecord) -> anyhow::Result<Option<(TradeInput, String)>> {
+        let Some(symbol) = self.get(rec, "symbol") else {
+            return Ok(None);
+        };
+
+        let input = TradeInput {
+            market: self.get(rec, "market").unwrap_or("futures").to_string(),
+            symbol: symbol.to_string(),
+            side: self.side(rec)?,
+            qty: self.number(rec, "qty")?.context("missing value for qty")?.abs(),
+            entry_time_utc: self.time(rec, "entry_time")?,
+            exit_time_utc: self.time(rec, "exit_time")?,
+            timezone: self.tz.to_string(),
+            session: self.get(rec, "session").unwrap_or("other").to_string(),
+            entry_price: self.number(rec, "entry_price")?,
+            exit_price: self.number(rec, "exit_price")?,
+            pnl_amount: self.number(rec, "pnl")?,
+            pnl_includes_fees: self.profile.pnl_includes_fees,
+            fees: self.number(rec, "fees")?.unwrap_or(0.0).abs(),
+            notes: self.get(rec, "notes").unwrap_or_default().to_string(),
+            instrument: self.get(rec, "instrument").map(str::to_string),
+            account_id: None,
+            tags: None,
+            rules_checked: None,
+        };
+        Ok(Some((input, self.get(rec, "account").unwrap_or_default().to_string())))
+    }
+}
+
+/// Parse a number with explicit separators. Currency symbols, codes and
+/// spaces are ignored; "(12.50)", "$-3.10" and a trailing "-" are negative.
+fn parse_number(s: &str, decimal: &str, thousands: &str) -> anyhow::Result<f64> {
+    let raw = s.trim();
+    // Look for the sign only once the currency decoration is gone: "$(12.50)", "-€3", "12.50- USD".
+    let signed: String = raw.chars().filter(|c| !(c.is_whitespace() || c.is_alphabetic() || "$€£¥".contains(*c))).collect();
+    let negative =
+        signed.starts_with('-') || signed.ends_with('-') || (signed.starts_with('(') && signed.ends_with(')'));
+    let decimal = decimal.chars().next().unwrap_or('.');
+    let thousands = thousands.chars().next();
+
+    let mut normalized = String::new();
+    for c in raw.chars() {
+        if c.is_ascii_digit() {
+            normalized.push(c);
+        } else if c == decimal {
+            normalized.push('.');
+        } else if Some(c) != thousands && !(c.is_whitespace() || c.is_alphabetic() || "()+-$€£¥".contains(c)) {
+            // e.g. a "." in a file the profile says uses "," decimals and " " thousands.
+            anyhow::bail!("unexpected '{c}' in number: {raw}");
+        }
+    }
+    let value: f64 = normalized.parse().with_context(|| format!("invalid number: {raw}"))?;
+    Ok(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_survives_currency_symbols() {
+        assert_eq!(parse_number("$(12.50)", ".", ",").unwrap(), -12.5);
+        assert_eq!(parse_number("$-3.10", ".", ",").unwrap(), -3.1);
+        assert_eq!(parse_number("-$1,234.50", ".", ",").unwrap(), -1234.5);
+        assert_eq!(parse_number("12.50- USD", ".", ",").unwrap(), -12.5);
+        assert_eq!(parse_number("(1.234,50 €)", ",", ".").unwrap(), -1234.5);
+        assert_eq!(parse_number("$ 12.50", ".", ",").unwrap(), 12.5);
+        assert_eq!(parse_number("+7", ".", ",").unwrap(), 7.0);
+    }
+
+    #[test]
+    fn two_columns_for_one_field_are_rejected() {
+        let mut input: ImportProfileInput = serde_json::from_value(serde_json::json!({
+            "name": "dupes",
+            "columns": {"Sym": "symbol", "Side": "side", "Qty": "qty", "In": "entry_time", "Out": "exit_time"},
+            "decimal_separator": ".",
+            "thousands_separator": ",",
+            "pnl_includes_fees": true
+        }))
+        .unwrap();
+        validate_profile(&input).unwrap();
+        input.columns.insert("Ticker".into(), "symbol".into());
+        assert!(validate_profile(&input).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::csv_import::CsvImportResult;
+use crate::models::{ExecutionInput, ImportBatch, TradeInput};
+
+/// sha256 of the file's contents, hex.
+pub fn file_hash(path: &Path) -> anyhow::Result<String> {
+    let bytes = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// An earlier import of byte-identical contents from the same source that has
+/// not been undone.
+pub fn find_identical_import(conn: &Connection, source: &str, file_hash: &str) -> anyhow::Result<Option<String>> {
+    let id = conn
+        .query_row(
+            "SELECT id FROM imports WHERE source = ?1 AND file_hash = ?2 ORDER BY imported_at_utc DESC LIMIT 1",
+            params![source, file_hash],
+            |r| r.get(0),
+        )
+        .optional()?;
+    Ok(id)
+}
+
+/// Record a new import batch for `path`. Counts are filled in by `finish_import`.
+pub fn start_import(conn: &Connection, source: &str, path: &Path, file_hash: &str) -> anyhow::Result<String> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT INTO imports (id, source, file_name, file_hash, imported_at_utc) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, source, file_name, file_hash, now],
+    )?;
+    Ok(id)
+}
+
+pub fn finish_import(conn: &Connection, result: &CsvImportResult, rows_total: i64) -> anyhow::Result<()> {
+    let Some(import_id) = result.import_id.as_deref() else {
+        return Ok(());
+    };
+    conn.execute(
+        "UPDATE imports SET rows_total=?2, created=?3, skipped=?4, errors=?5 WHERE id=?1",
+        params![import_id, rows_total, result.created, result.skipped, result.errors.len() as i64],
+    )?;
+    Ok(())
+}
+
+pub fn list_imports(conn: &Connection, limit: i64, offset: i64) -> anyhow::Result<Vec<ImportBatch>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, source, file_name, file_hash, rows_total, created, skipped, errors, imported_at_utc
+         FROM imports
+         ORDER BY imported_at_utc DESC
+         LIMIT ?1 OFFSET ?2",
+    )?;
+    let rows = stmt.query_map(params![limit, offset], |row| {
+        Ok(ImportBatch {
+            id: row.get(0)?,
+            source: row.get(1)?,
+            file_name: row.get(2)?,
+            file_hash: row.get(3)?,
+            rows_total: row.get(4)?,
+            created: row.get(5)?,
+            skipped: row.get(6)?,
+            errors: row.get(7)?,
+            imported_at_utc: row.get(8)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+/// Remove everything an import added: its trades, its fills and any trade built
+/// from those fills. Fills from other imports that were paired with them become
+/// unmatched again.
+pub fn undo_import(conn: &Connection, import_id: &str) -> anyhow::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM trades
+         WHERE import_id = ?1
+            OR id IN (
+                SELECT te.trade_id FROM trade_executions te
+                JOIN executions e ON e.id = te.execution_id
+                WHERE e.import_id = ?1
+            )",
+        params![import_id],
+    )?;
+    tx.execute("DELETE FROM executions WHERE import_id = ?1", params![import_id])?;
+    let n = tx.execute("DELETE FROM imports WHERE id = ?1", params![import_id])?;
+    if n == 0 {
+        anyhow::bail!("import not found");
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Stable identity of an imported trade, so re-importing the same file (or an
+/// overlapping export) does not create it twice.
+pub fn trade_fingerprint(source: &str, account: &str, input: &TradeInput) -> String {
+    let price = |p: Option<f64>| p.map(|p| p.to_string()).unwrap_or_default();
+    let key = format!(
+        "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+        source,
+        account.trim(),
+        input.symbol.trim().to_uppercase(),
+        input.side.trim().to_lowercase(),
+        input.qty,
+        input.entry_time_utc,
+        input.exit_time_utc,
+        price(input.entry_price),
+        price(input.exit_price),
+    );
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+pub fn fingerprint_exists(conn: &Connection, fingerprint: &str) -> anyhow::Result<bool> {
+    let n: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM trades WHERE import_fingerprint = ?1",
+        params![fingerprint],
+        |r| r.get(0),
+    )?;
+    Ok(n > 0)
+}
+
+/// Identity of an imported fill that has no broker id, built from the same
+/// kind of key as `trade_fingerprint`.
+pub fn execution_fingerprint(input: &ExecutionInput) -> anyhow::Result<String> {
+    let key = format!(
+        "{}|{}|{}|{}|{}|{}|{}",
+        input.source,
+        input.account.trim(),
+        input.symbol.trim().to_uppercase(),
+        crate::executions::normalize_side(&input.side)?,
+        input.qty,
+        input.price,
+        input.time_utc,
+    );
+    Ok(format!("{:x}", Sha256::digest(key.as_bytes())))
+}
+
+/// Whether an earlier import already stored this fill. Fills from the current
+/// import are not counted: a file may list two identical fills.
+pub fn execution_fingerprint_exists(conn: &Connection, fingerprint: &str, import_id: &str) -> anyhow::Result<bool> {
+    let n: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM executions WHERE import_fingerprint = ?1 AND import_id IS NOT ?2",
+        params![fingerprint, import_id],
+        |r| r.get(0),
+    )?;
+    Ok(n > 0)
+}
+
+pub fn tag_trade(conn: &Connection, trade_id: &str, import_id: &str, fingerprint: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE trades SET import_id=?2, import_fingerprint=?3 WHERE id=?1",
+        params![trade_id, import_id, fingerprint],
+    )?;
+    Ok(())
+}
+
+pub fn tag_execution(
+    conn: &Connection,
+    execution_id: &str,
+    import_id: &str,
+    fingerprint: Option<&str>,
+) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE executions SET import_id=?2, import_fingerprint=?3 WHERE id=?1",
+        params![execution_id, import_id, fingerprint],
+    )?;
+    Ok(())
+}
+
+/// Attribute trades the matcher just built from this import's fills to the import.
+pub fn tag_built_trades(conn: &Connection, import_id: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE trades SET import_id=?1
+         WHERE import_id IS NULL
+           AND id IN (
+               SELECT te.trade_id FROM trade_executions te
+               JOIN executions e ON e.id = te.execution_id
+               WHERE e.import_id = ?1
+           )",
+        params![import_id],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::models::Instrument;
+
+// CME month codes, Jan..Dec.
+const MONTH_CODES: &str = "FGHJKMNQUVXZ";
+
+fn instrument_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Instrument> {
+    Ok(Instrument {
+        root_symbol: row.get(0)?,
+        name: row.get(1)?,
+        exchange: row.get(2)?,
+        currency: row.get(3)?,
+        tick_size: row.get(4)?,
+        tick_value: row.get(5)?,
+        point_value: row.get(6)?,
+    })
+}
+
+pub fn list_instruments(conn: &Connection) -> anyhow::Result<Vec<Instrument>> {
+    let mut stmt = conn.prepare(
+        "SELECT root_symbol, name, exchange, currency, tick_size, tick_value, point_value
+         FROM instruments
+         ORDER BY exchange ASC, root_symbol ASC",
+    )?;
+    let rows = stmt.query_map([], instrument_from_row)?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+pub fn get_instrument(conn: &Connection, root_symbol: &str) -> anyhow::Result<Option<Instrument>> {
+    let inst = conn
+        .query_row(
+            "SELECT root_symbol, name, exchange, currency, tick_size, tick_value, point_value
+             FROM instruments WHERE root_symbol = ?1",
+            params![root_symbol],
+            instrument_from_row,
+        )
+        .optional()?;
+    Ok(inst)
+}
+
+pub fn upsert_instrument(conn: &Connection, inst: &Instrument) -> anyhow::Result<()> {
+    validate_instrument(inst)?;
+    conn.execute(
+        "INSERT INTO instruments (root_symbol, name, exchange, currency, tick_size, tick_value, point_value)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(root_symbol) DO UPDATE SET
+            name=excluded.name, exchange=excluded.exchange, currency=excluded.currency,
+            tick_size=excluded.tick_size, tick_value=excluded.tick_value, point_value=excluded.point_value",
+        params![
+            inst.root_symbol.trim().to_uppercase(),
+            inst.name,
+            inst.exchange,
+            inst.currency,
+            inst.tick_size,
+            inst.tick_value,
+            inst.point_value
+        ],
+    )?;
+    Ok(())
+}
+
+/// Add `inst` unless its root is already in the catalog. Returns whether it
+/// was added; importers use this so they never overwrite curated values.
+pub fn insert_instrument_if_missing(conn: &Connection, inst: &Instrument) -> anyhow::Result<bool> {
+    validate_instrument(inst)?;
+    let inserted = conn.execute(
+        "INSERT INTO instruments (root_symbol, name, exchange, currency, tick_size, tick_value, point_value)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(root_symbol) DO NOTHING",
+        params![
+            inst.root_symbol.trim().to_uppercase(),
+            inst.name,
+            inst.exchange,
+            inst.currency,
+            inst.tick_size,
+            inst.tick_value,
+            inst.point_value
+        ],
+    )?;
+    Ok(inserted > 0)
+}
+
+pub fn delete_instrument(conn: &Connection, root_symbol: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM instruments WHERE root_symbol = ?1", params![root_symbol])?;
+    Ok(())
+}
+
+/// Find the catalog entry for a traded symbol.
+///
+/// Accepts bare roots ("MNQ"), exchange contract codes ("MNQH5", "MESZ24"),
+/// NinjaTrader names ("MNQ 03-25") and ToS-style slashes ("/ES").
+pub fn resolve_instrument(conn: &Connection, symbol: &str) -> anyhow::Result<Option<Instrument>> {
+    for candidate in root_candidates(symbol) {
+        if let Some(inst) = get_instrument(conn, &candidate)? {
+            return Ok(Some(inst));
+        }
+    }
+    Ok(None)
+}
+
+/// Exchange-style contract code, e.g. ("MNQ", 3, 2025) -> "MNQH5".
+pub fn contract_symbol(root: &str, month: u32, year: i32) -> anyhow::Result<String> {
+    if !(1..=12).contains(&month) {
+        anyhow::bail!("invalid contract month: {month}");
+    }
+    let code = MONTH_CODES.as_bytes()[(month - 1) as usize] as char;
+    Ok(format!("{}{}{}", root.trim().to_uppercase(), code, year.rem_euclid(10)))
+}
+
+fn root_candidates(symbol: &str) -> Vec<String> {
+    let token = symbol
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .trim_start_matches('/')
+        .to_uppercase();
+    if token.is_empty() {
+        return Vec::new();
+    }
+
+    let mut out = vec![token.clone()];
+
+    // Strip a trailing "<month code><year>" contract suffix, e.g. MESZ4 -> MES.
+    let without_year = token.trim_end_matches(|c: char| c.is_ascii_digit());
+    if without_year.len() < token.len() {
+        if let Some(code) = without_year.chars().last() {
+            if MONTH_CODES.contains(code) && without_year.len() > 1 {
+                out.push(without_year[..without_year.len() - 1].to_string());
+            }
+        }
+    }
+
+    out
+}
+
+fn validate_instrument(inst: &Instrument) -> anyhow::Result<()> {
+    if inst.root_symbol.trim().is_empty() {
+        anyhow::bail!("root symbol is required");
+    }
+    if inst.tick_size <= 0.0 || inst.tick_value <= 0.0 || inst.point_value <= 0.0 {
+        anyhow::bail!("tick size, tick value and point value must be > 0");
+    }
+    Ok(())
+}
+
+/// Resolve the instrument a trade should reference: an explicit root wins, otherwise
+/// it is derived from the symbol. Unknown symbols are allowed and stay unlinked.
+pub fn instrument_for_trade(
+    conn: &Connection,
+    explicit: Option<&str>,
+    symbol: &str,
+) -> anyhow::Result<Option<Instrument>> {
+    if let Some(root) = explicit.map(str::trim).filter(|s| !s.is_empty()) {
+        let inst = get_instrument(conn, &root.to_uppercase())?
+            .with_context(|| format!("unknown instrument: {root}"))?;
+        return Ok(Some(inst));
+    }
+    resolve_instrument(conn, symbol)
+}
@@ -5,11 +5,11 @@ use rusqlite::{params, Connection};
 
 use crate::models::{DaySummary, TradeHighlight};
 
-fn parse_tz(tz: &str) -> anyhow::Result<Tz> {
+pub(crate) fn parse_tz(tz: &str) -> anyhow::Result<Tz> {
     tz.parse::<Tz>().with_context(|| format!("invalid timezone: {tz}"))
 }
 
-fn local_date_str(tz: Tz, utc_ms: i64) -> String {
+pub(crate) fn local_date_str(tz: Tz, utc_ms: i64) -> String {
     let dt_utc: DateTime<Utc> = Utc
         .timestamp_millis_opt(utc_ms)
         .single()
@@ -18,7 +18,7 @@ fn local_date_str(tz: Tz, utc_ms: i64) -> String {
     dt_local.format("%Y-%m-%d").to_string()
 }
 
-fn utc_range_for_local_date(tz: Tz, date_local: &str) -> anyhow::Result<(i64, i64)> {
+pub(crate) fn utc_range_for_local_date(tz: Tz, date_local: &str) -> anyhow::Result<(i64, i64)> {
     let d = NaiveDate::parse_from_str(date_local, "%Y-%m-%d")
         .with_context(|| format!("invalid date_local: {date_local}"))?;
     let start_local = tz
@@ -33,7 +33,13 @@ fn utc_range_for_local_date(tz: Tz, date_local: &str) -> anyhow::Result<(i64, i6
     ))
 }
 
-pub fn month_summary(conn: &Connection, tz_name: &str, year: i32, month: u32) -> anyhow::Result<Vec<DaySummary>> {
+pub fn month_summary(
+    conn: &Connection,
+    tz_name: &str,
+    year: i32,
+    month: u32,
+    account_id: Option<&str>,
+) -> anyhow::Result<Vec<DaySummary>> {
     let tz = parse_tz(tz_name)?;
 
     // Compute UTC range covering the month in that timezone.
@@ -54,10 +60,11 @@ pub fn month_summary(conn: &Connection, tz_name: &str, year: i32, month: u32) ->
     let mut stmt = conn.prepare(
         "SELECT exit_time_utc, pnl_net
          FROM trades
-         WHERE exit_time_utc >= ?1 AND exit_time_utc < ?2",
+         WHERE exit_time_utc >= ?1 AND exit_time_utc < ?2
+           AND (?3 IS NULL OR account_id = ?3)",
     )?;
 
-    let rows = stmt.query_map(params![start_utc_ms, end_utc_ms], |row| {
+    let rows = stmt.query_map(params![start_utc_ms, end_utc_ms, account_id], |row| {
         let exit_time_utc: i64 = row.get(0)?;
         let pnl_net: f64 = row.get(1)?;
         Ok((exit_time_utc, pnl_net))
@@ -85,7 +92,12 @@ pub fn month_summary(conn: &Connection, tz_name: &str, year: i32, month: u32) ->
     Ok(out)
 }
 
-pub fn day_trades(conn: &Connection, tz_name: &str, date_local: &str) -> anyhow::Result<Vec<TradeHighlight>> {
+pub fn day_trades(
+    conn: &Connection,
+    tz_name: &str,
+    date_local: &str,
+    account_id: Option<&str>,
+) -> anyhow::Result<Vec<TradeHighlight>> {
     let tz = parse_tz(tz_name)?;
     let (start_utc_ms, end_utc_ms) = utc_range_for_local_date(tz, date_local)?;
 
@@ -93,10 +105,11 @@ pub fn day_trades(conn: &Connection, tz_name: &str, date_local: &str) -> anyhow:
         "SELECT id, symbol, qty, pnl_net, notes, exit_time_utc
          FROM trades
          WHERE exit_time_utc >= ?1 AND exit_time_utc < ?2
+           AND (?3 IS NULL OR account_id = ?3)
          ORDER BY exit_time_utc ASC",
     )?;
 
-    let rows = stmt.query_map(params![start_utc_ms, end_utc_ms], |row| {
+    let rows = stmt.query_map(params![start_utc_ms, end_utc_ms, account_id], |row| {
         Ok(TradeHighlight {
             id: row.get(0)?,
             symbol: row.get(1)?,
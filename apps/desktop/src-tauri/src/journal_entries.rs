@@ -53,3 +53,23 @@ pub fn upsert_daily_entry(conn: &Connection, date_local: &str, text: &str) -> an
 
     Ok(())
 }
+
+/// Id of the day's journal entry, creating an empty one if needed.
+pub fn ensure_daily_entry(conn: &Connection, date_local: &str) -> anyhow::Result<String> {
+    chrono::NaiveDate::parse_from_str(date_local, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("invalid date_local: {date_local}"))?;
+
+    let find = |conn: &Connection| -> rusqlite::Result<Option<String>> {
+        conn.query_row(
+            "SELECT id FROM journal_entries WHERE date_local = ?1 AND type = 'daily'",
+            params![date_local],
+            |r| r.get(0),
+        )
+        .optional()
+    };
+    if let Some(id) = find(conn)? {
+        return Ok(id);
+    }
+    upsert_daily_entry(conn, date_local, "")?;
+    find(conn)?.ok_or_else(|| anyhow::anyhow!("journal entry not created"))
+}
@@ -5,10 +5,28 @@ mod db_seed;
 mod models;
 mod settings;
 mod trades;
+mod accounts;
+mod prop_rules;
+mod trade_filter;
+mod analytics;
+mod search;
+mod tags;
+mod attachments;
 mod journal;
 mod backup;
+mod rekey;
+mod recovery;
 mod journal_entries;
 mod csv_import;
+mod csv_ninjatrader;
+mod csv_tradovate;
+mod csv_rithmic;
+mod ibkr_flex;
+mod import_profiles;
+mod imports;
+mod instruments;
+mod executions;
+mod matcher;
 
 use crate::db::DbState;
 use tauri::Manager;
@@ -31,6 +49,7 @@ pub fn run() {
             if let Err(err) = commands::try_autoload(&handle, state.inner()) {
                 eprintln!("FTJournal autoload failed: {err:?}");
             }
+            commands::spawn_idle_lock(handle.clone());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -38,23 +57,73 @@ pub fn run() {
             commands::app_get_status,
             commands::db_init,
             commands::db_unlock,
+            commands::db_lock,
+            commands::db_set_auto_lock,
+            commands::db_change_passphrase,
+            commands::db_encrypt,
+            commands::db_decrypt,
+            commands::db_upgrade_cipher,
             commands::settings_get,
             commands::settings_update,
             commands::rules_list,
             commands::rules_upsert,
             commands::rules_delete,
+            commands::instruments_list,
+            commands::instruments_upsert,
+            commands::instruments_delete,
+            commands::accounts_list,
+            commands::accounts_create,
+            commands::accounts_update,
+            commands::accounts_delete,
+            commands::prop_rules_get,
+            commands::prop_rules_set,
+            commands::prop_rules_delete,
+            commands::prop_report,
+            commands::analytics_stats,
+            commands::analytics_equity_curve,
+            commands::analytics_breakdown,
+            commands::analytics_rule_adherence,
+            commands::search,
+            commands::tag_groups_list,
+            commands::tag_groups_upsert,
+            commands::tag_groups_delete,
+            commands::tags_list,
+            commands::tags_create,
+            commands::tags_update,
+            commands::tags_delete,
+            commands::analytics_tag_performance,
+            commands::attachments_add,
+            commands::attachments_list,
+            commands::attachments_get,
+            commands::attachments_delete,
             commands::trades_list,
+            commands::trades_query,
             commands::trades_get,
             commands::trades_create,
             commands::trades_update,
             commands::trades_delete,
+            commands::executions_list,
+            commands::executions_create,
+            commands::executions_delete,
+            commands::executions_build_trades,
             commands::journal_month_summary,
             commands::journal_day_trades,
             commands::backup_export,
             commands::backup_import,
             commands::journal_entry_get,
             commands::journal_entry_upsert,
-            commands::csv_import_generic
+            commands::csv_import_generic,
+            commands::csv_import_preview,
+            commands::import_profiles_list,
+            commands::import_profiles_create,
+            commands::import_profiles_update,
+            commands::import_profiles_delete,
+            commands::imports_list,
+            commands::imports_undo,
+            commands::csv_import_ninjatrader,
+            commands::csv_import_tradovate,
+            commands::csv_import_rithmic,
+            commands::ibkr_import_flex
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,340 @@
+use std::collections::VecDeque;
+
+// Quantities are REAL in the DB; anything smaller than this is rounding dust.
+const QTY_EPS: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMethod {
+    /// Every closing fill becomes its own trade, paired with the oldest open lots.
+    Fifo,
+    /// One trade per flat-to-flat position, priced at the average of its entries and exits.
+    AverageCost,
+}
+
+/// A fill (or the not-yet-matched remainder of one) fed into the matcher.
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub execution_id: String,
+    pub is_buy: bool,
+    pub qty: f64,
+    pub price: f64,
+    pub fees: f64,
+    pub time_utc: i64,
+    /// Broker-reported PnL for the quantity this fill closes, net of commissions.
+    pub realized_pnl: Option<f64>,
+}
+
+/// A closed round trip and the fill quantities it consumed.
+#[derive(Debug, Clone)]
+pub struct RoundTrip {
+    pub side: &'static str, // "long" | "short"
+    pub qty: f64,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    pub entry_time_utc: i64,
+    pub exit_time_utc: i64,
+    pub fees: f64,
+    pub realized_pnl: Option<f64>, // FIFO only: taken from the closing fill
+    pub legs: Vec<(String, f64)>, // (execution_id, qty)
+}
+
+#[derive(Debug, Clone)]
+struct Lot {
+    execution_id: String,
+    qty: f64,
+    price: f64,
+    fee_per_unit: f64,
+    time_utc: i64,
+}
+
+#[derive(Debug, Default)]
+struct Leg {
+    qty: f64,
+    notional: f64,
+    fees: f64,
+}
+
+impl Leg {
+    fn add(&mut self, qty: f64, price: f64, fees: f64) {
+        self.qty += qty;
+        self.notional += qty * price;
+        self.fees += fees;
+    }
+
+    fn avg_price(&self) -> f64 {
+        self.notional / self.qty
+    }
+}
+
+/// Pair fills for a single account + contract into round trips.
+///
+/// `fills` must be in execution order. Scale-ins add lots, partial exits close
+/// part of the position, and a fill that crosses zero closes the old position
+/// and opens the opposite one with its remainder. Fills belonging to a position
+/// that is still open at the end are left out of the result.
+pub fn match_fills(fills: &[Fill], method: MatchMethod) -> Vec<RoundTrip> {
+    let mut out = Vec::new();
+    let mut lots: VecDeque<Lot> = VecDeque::new();
+    let mut long = true;
+    let mut opened_at = 0i64;
+
+    // AverageCost bookkeeping for the current flat-to-flat position.
+    let mut opened = Leg::default();
+    let mut closed = Leg::default();
+    let mut legs: Vec<(String, f64)> = Vec::new();
+
+    for fill in fills {
+        if fill.qty <= QTY_EPS {
+            continue;
+        }
+        let fee_per_unit = fill.fees / fill.qty;
+        let mut remaining = fill.qty;
+
+        while remaining > QTY_EPS {
+            let open_qty: f64 = lots.iter().map(|l| l.qty).sum();
+
+            if open_qty <= QTY_EPS || long == fill.is_buy {
+                // Opening or adding to a position.
+                if open_qty <= QTY_EPS {
+                    long = fill.is_buy;
+                    opened_at = fill.time_utc;
+                }
+                lots.push_back(Lot {
+                    execution_id: fill.execution_id.clone(),
+                    qty: remaining,
+                    price: fill.price,
+                    fee_per_unit,
+                    time_utc: fill.time_utc,
+                });
+                opened.add(remaining, fill.price, remaining * fee_per_unit);
+                legs.push((fill.execution_id.clone(), remaining));
+                break;
+            }
+
+            let close_qty = remaining.min(open_qty);
+            let consumed = consume_lots(&mut lots, close_qty);
+            remaining -= close_qty;
+
+            match method {
+                MatchMethod::Fifo => {
+                    let mut entry = Leg::default();
+                    let mut trip_legs = Vec::new();
+                    for lot in &consumed {
+                        entry.add(lot.qty, lot.price, lot.qty * lot.fee_per_unit);
+                        trip_legs.push((lot.execution_id.clone(), lot.qty));
+                    }
+                    trip_legs.push((fill.execution_id.clone(), close_qty));
+                    out.push(RoundTrip {
+                        side: side_label(long),
+                        qty: close_qty,
+                        entry_price: entry.avg_price(),
+                        exit_price: fill.price,
+                        entry_time_utc: consumed.iter().map(|l| l.time_utc).min().unwrap_or(fill.time_utc),
+                        exit_time_utc: fill.time_utc,
+                        fees: entry.fees + close_qty * fee_per_unit,
+                        realized_pnl: fill.realized_pnl,
+                        legs: trip_legs,
+                    });
+                }
+                MatchMethod::AverageCost => {
+                    closed.add(close_qty, fill.price, close_qty * fee_per_unit);
+                    legs.push((fill.execution_id.clone(), close_qty));
+                }
+            }
+
+            if lots.is_empty() {
+                if method == MatchMethod::AverageCost {
+                    out.push(RoundTrip {
+                        side: side_label(long),
+                        qty: closed.qty,
+                        entry_price: opened.avg_price(),
+                        exit_price: closed.avg_price(),
+                        entry_time_utc: opened_at,
+                        exit_time_utc: fill.time_utc,
+                        fees: opened.fees + closed.fees,
+                        realized_pnl: None,
+                        legs: std::mem::take(&mut legs),
+                    });
+                }
+                opened = Leg::default();
+                closed = Leg::default();
+                legs.clear();
+            }
+        }
+    }
+
+    out
+}
+
+fn consume_lots(lots: &mut VecDeque<Lot>, mut qty: f64) -> Vec<Lot> {
+    let mut consumed = Vec::new();
+    while qty > QTY_EPS {
+        let Some(front) = lots.front_mut() else {
+            break;
+        };
+        let take = front.qty.min(qty);
+        consumed.push(Lot {
+            qty: take,
+            ..front.clone()
+        });
+        front.qty -= take;
+        qty -= take;
+        if front.qty <= QTY_EPS {
+            lots.pop_front();
+        }
+    }
+    consumed
+}
+
+fn side_label(long: bool) -> &'static str {
+    if long {
+        "long"
+    } else {
+        "short"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(id: &str, is_buy: bool, qty: f64, price: f64, fees: f64, time_utc: i64) -> Fill {
+        Fill {
+            execution_id: id.to_string(),
+            is_buy,
+            qty,
+            price,
+            fees,
+            time_utc,
+            realized_pnl: None,
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn scale_in() {
+        let fills = [
+            fill("b1", true, 1.0, 100.0, 0.0, 1),
+            fill("b2", true, 1.0, 102.0, 0.0, 2),
+            fill("s1", false, 2.0, 105.0, 0.0, 3),
+        ];
+
+        let fifo = match_fills(&fills, MatchMethod::Fifo);
+        assert_eq!(fifo.len(), 1);
+        assert_eq!(
+            (fifo[0].side, fifo[0].qty, fifo[0].entry_time_utc),
+            ("long", 2.0, 1)
+        );
+        assert!(close(fifo[0].entry_price, 101.0));
+        assert_eq!(
+            fifo[0].legs,
+            vec![("b1".into(), 1.0), ("b2".into(), 1.0), ("s1".into(), 2.0)]
+        );
+
+        let avg = match_fills(&fills, MatchMethod::AverageCost);
+        assert_eq!(avg.len(), 1);
+        assert!(close(avg[0].entry_price, 101.0) && close(avg[0].exit_price, 105.0));
+    }
+
+    #[test]
+    fn partial_exit() {
+        let fills = [
+            fill("b1", true, 3.0, 100.0, 0.0, 1),
+            fill("s1", false, 1.0, 101.0, 0.0, 2),
+            fill("s2", false, 2.0, 99.0, 0.0, 3),
+        ];
+
+        let fifo = match_fills(&fills, MatchMethod::Fifo);
+        let trips: Vec<(f64, f64)> = fifo.iter().map(|t| (t.qty, t.exit_price)).collect();
+        assert_eq!(trips, vec![(1.0, 101.0), (2.0, 99.0)]);
+
+        let avg = match_fills(&fills, MatchMethod::AverageCost);
+        assert_eq!(avg.len(), 1);
+        assert_eq!((avg[0].qty, avg[0].exit_time_utc), (3.0, 3));
+        assert!(close(avg[0].exit_price, (101.0 + 2.0 * 99.0) / 3.0));
+
+        // Still open after a partial exit: nothing for the open remainder.
+        assert_eq!(match_fills(&fills[..2], MatchMethod::AverageCost).len(), 0);
+        assert_eq!(match_fills(&fills[..2], MatchMethod::Fifo).len(), 1);
+    }
+
+    #[test]
+    fn flip_through_zero() {
+        let fills = [
+            fill("b1", true, 1.0, 100.0, 0.0, 1),
+            fill("s1", false, 3.0, 104.0, 0.0, 2),
+            fill("b2", true, 2.0, 101.0, 0.0, 3),
+        ];
+
+        for method in [MatchMethod::Fifo, MatchMethod::AverageCost] {
+            let trips = match_fills(&fills, method);
+            let summary: Vec<(&str, f64, f64, f64)> = trips
+                .iter()
+                .map(|t| (t.side, t.qty, t.entry_price, t.exit_price))
+                .collect();
+            assert_eq!(
+                summary,
+                vec![("long", 1.0, 100.0, 104.0), ("short", 2.0, 104.0, 101.0)],
+                "{method:?}"
+            );
+            // The crossing fill is split between the two trades.
+            assert_eq!(trips[0].legs.last(), Some(&("s1".to_string(), 1.0)));
+            assert_eq!(trips[1].legs.first(), Some(&("s1".to_string(), 2.0)));
+            assert_eq!(trips[1].entry_time_utc, 2);
+        }
+    }
+
+    #[test]
+    fn fractional_qty_within_eps_closes_flat() {
+        // 0.1 + 0.1 + 0.1 != 0.3 in floating point.
+        let fills = [
+            fill("b1", true, 0.1, 10.0, 0.0, 1),
+            fill("b2", true, 0.1, 10.0, 0.0, 2),
+            fill("b3", true, 0.1, 10.0, 0.0, 3),
+            fill("s1", false, 0.3, 11.0, 0.0, 4),
+            fill("dust", true, QTY_EPS / 2.0, 10.0, 0.0, 5),
+        ];
+        for method in [MatchMethod::Fifo, MatchMethod::AverageCost] {
+            let trips = match_fills(&fills, method);
+            assert_eq!(trips.len(), 1, "{method:?}");
+            assert!(close(trips[0].qty, 0.3));
+        }
+        // A following fill opens a fresh position rather than closing dust.
+        let mut more = fills.to_vec();
+        more.push(fill("s2", false, 1.0, 12.0, 0.0, 6));
+        more.push(fill("b4", true, 1.0, 11.0, 0.0, 7));
+        let trips = match_fills(&more, MatchMethod::AverageCost);
+        assert_eq!(trips.len(), 2);
+        assert_eq!((trips[1].side, trips[1].qty), ("short", 1.0));
+    }
+
+    #[test]
+    fn fees_are_prorated_by_quantity() {
+        let fills = [
+            fill("b1", true, 4.0, 100.0, 4.0, 1),
+            fill("s1", false, 1.0, 101.0, 2.0, 2),
+            fill("s2", false, 3.0, 101.0, 3.0, 3),
+        ];
+
+        let fifo = match_fills(&fills, MatchMethod::Fifo);
+        // Entry commission splits 1/4 : 3/4 between the exits.
+        assert!(close(fifo[0].fees, 1.0 + 2.0));
+        assert!(close(fifo[1].fees, 3.0 + 3.0));
+
+        // A flip charges each side its share of the crossing fill's commission.
+        let flip = [
+            fill("b1", true, 1.0, 100.0, 1.0, 1),
+            fill("s1", false, 2.0, 101.0, 4.0, 2),
+            fill("b2", true, 1.0, 99.0, 1.0, 3),
+        ];
+        let trips = match_fills(&flip, MatchMethod::AverageCost);
+        assert!(close(trips[0].fees, 1.0 + 2.0));
+        assert!(close(trips[1].fees, 2.0 + 1.0));
+        let total: f64 = trips.iter().map(|t| t.fees).sum();
+        assert!(close(total, 6.0));
+    }
+}
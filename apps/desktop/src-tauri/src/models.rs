@@ -12,6 +12,17 @@ pub struct Settings {
     pub timezone: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instrument {
+    pub root_symbol: String,
+    pub name: String,
+    pub exchange: String,
+    pub currency: String,
+    pub tick_size: f64,
+    pub tick_value: f64,
+    pub point_value: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub id: String,
@@ -29,6 +40,12 @@ pub struct Trade {
     pub pnl_net: f64,
     pub pnl_gross: f64,
     pub notes: String,
+    pub instrument: Option<String>,
+    pub entry_price: Option<f64>,
+    pub exit_price: Option<f64>,
+    pub pnl_computed: Option<f64>, // gross, from prices x point value
+    pub pnl_mismatch: i64,         // 1 when pnl_amount disagrees with pnl_computed
+    pub account_id: Option<String>,
     pub created_at_utc: i64,
     pub updated_at_utc: i64,
 }
@@ -43,10 +60,24 @@ pub struct TradeInput {
     pub exit_time_utc: i64,
     pub timezone: String,
     pub session: String,
-    pub pnl_amount: f64,
+    #[serde(default)]
+    pub entry_price: Option<f64>,
+    #[serde(default)]
+    pub exit_price: Option<f64>,
+    /// Manual override. When omitted, PnL is computed from prices and the instrument.
+    #[serde(default)]
+    pub pnl_amount: Option<f64>,
     pub pnl_includes_fees: bool,
     pub fees: f64,
     pub notes: String,
+    /// Catalog root symbol (e.g. "MNQ"). Derived from `symbol` when omitted.
+    #[serde(default)]
+    pub instrument: Option<String>,
+    #[serde(default)]
+    pub account_id: Option<String>,
+    /// Tag ids. When omitted on update, the trade keeps its tags.
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
     pub rules_checked: Option<std::collections::HashMap<String, bool>>,
 }
 
@@ -55,6 +86,207 @@ pub struct TradeWithRules {
     pub trade: Trade,
     pub rules: Vec<Rule>,
     pub checked: std::collections::HashMap<String, bool>,
+    pub executions: Vec<LinkedExecution>, // fill ladder, oldest first
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Execution {
+    pub id: String,
+    pub source: String,
+    pub external_id: String,
+    pub account: String,
+    pub symbol: String,
+    pub instrument: Option<String>,
+    pub side: String, // buy | sell
+    pub qty: f64,
+    pub price: f64,
+    pub fees: f64,
+    pub realized_pnl: Option<f64>, // broker-reported, net of commissions
+    pub time_utc: i64,
+    pub created_at_utc: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionInput {
+    pub source: String,
+    #[serde(default)]
+    pub external_id: String,
+    #[serde(default)]
+    pub account: String,
+    pub symbol: String,
+    pub side: String,
+    pub qty: f64,
+    pub price: f64,
+    pub fees: f64,
+    #[serde(default)]
+    pub realized_pnl: Option<f64>,
+    pub time_utc: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedExecution {
+    pub execution: Execution,
+    pub qty: f64, // portion of the fill allocated to this trade
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagGroup {
+    pub id: String,
+    pub name: String, // Setup, Mistake, Market Condition, ...
+    pub sort_order: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub group_id: Option<String>,
+    pub name: String,
+    pub color: String, // CSS color; empty = default
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagInput {
+    #[serde(default)]
+    pub group_id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub color: String,
+}
+
+/// Column mapping for the generic CSV importer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProfile {
+    pub id: String,
+    pub name: String,
+    pub columns: std::collections::HashMap<String, String>, // source header -> field
+    pub datetime_format: String,                             // chrono format; empty = ISO
+    pub timezone: Option<String>,                            // None = app timezone
+    pub decimal_separator: String,
+    pub thousands_separator: String,
+    pub side_map: std::collections::HashMap<String, String>, // source value -> long | short
+    pub pnl_includes_fees: bool,
+    pub created_at_utc: i64,
+    pub updated_at_utc: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProfileInput {
+    pub name: String,
+    pub columns: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    pub datetime_format: String,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    pub decimal_separator: String,
+    #[serde(default)]
+    pub thousands_separator: String,
+    #[serde(default)]
+    pub side_map: std::collections::HashMap<String, String>,
+    pub pnl_includes_fees: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+    pub broker: String,
+    pub account_type: String, // eval | funded | personal | sim
+    pub starting_balance: f64,
+    pub currency: String,
+    pub created_at_utc: i64,
+    pub updated_at_utc: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountInput {
+    pub name: String,
+    #[serde(default)]
+    pub broker: String,
+    pub account_type: String,
+    pub starting_balance: f64,
+    pub currency: String,
+}
+
+/// How a prop account's drawdown threshold follows its balance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawdownMode {
+    /// Trails the closing balance of each session.
+    Eod,
+    /// Trails the closed-trade balance after every trade.
+    Intraday,
+    /// Fixed below the starting balance.
+    Static,
+}
+
+/// Evaluation rules of a prop-firm account. Unset limits are not checked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropRules {
+    pub account_id: String,
+    pub profit_target: Option<f64>,
+    pub max_drawdown: Option<f64>,
+    pub drawdown_mode: DrawdownMode,
+    pub drawdown_lock_balance: Option<f64>, // trailing threshold stops rising here
+    pub daily_loss_limit: Option<f64>,
+    pub consistency_pct: Option<f64>, // max share of total profit from the best day, 0-100
+    pub min_trading_days: Option<i64>,
+    pub updated_at_utc: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropRulesInput {
+    pub profit_target: Option<f64>,
+    pub max_drawdown: Option<f64>,
+    pub drawdown_mode: DrawdownMode,
+    pub drawdown_lock_balance: Option<f64>,
+    pub daily_loss_limit: Option<f64>,
+    pub consistency_pct: Option<f64>,
+    pub min_trading_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropViolation {
+    pub rule: String, // drawdown | daily_loss | consistency
+    pub trade_id: String,
+    pub exit_time_utc: i64,
+    pub session_date: String,
+    pub message: String,
+}
+
+/// State of an account after replaying its trades against its prop rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropReport {
+    pub account_id: String,
+    pub starting_balance: f64,
+    pub balance: f64,
+    pub high_water_mark: f64,
+    pub drawdown_threshold: Option<f64>,
+    pub drawdown_buffer: Option<f64>, // balance - threshold
+    pub daily_loss_remaining: Option<f64>, // for the current session
+    pub days_traded: i64,
+    pub min_trading_days: Option<i64>,
+    pub profit: f64,
+    pub profit_target: Option<f64>,
+    pub target_progress_pct: Option<f64>,
+    pub best_day_pnl: f64,
+    pub best_day_share_pct: Option<f64>,
+    pub violations: Vec<PropViolation>,
+    pub passed: bool,
+}
+
+/// One imported file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportBatch {
+    pub id: String,
+    pub source: String, // generic | ninjatrader | tradovate | rithmic | ibkr
+    pub file_name: String,
+    pub file_hash: String, // sha256, hex
+    pub rows_total: i64,
+    pub created: i64,
+    pub skipped: i64,
+    pub errors: i64,
+    pub imported_at_utc: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,3 +311,174 @@ pub struct JournalEntry {
     pub date_local: String,
     pub text: String,
 }
+
+/// Which trades an analytics or list query covers. Unset fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TradeFilter {
+    pub account_id: Option<String>,
+    pub symbol: Option<String>, // substring, case-insensitive
+    pub symbols: Vec<String>,   // any of, exact (case-insensitive)
+    pub instrument: Option<String>, // root symbol
+    pub session: Option<String>,
+    pub sessions: Vec<String>, // any of
+    pub side: Option<String>,    // long | short
+    pub outcome: Option<String>, // win | loss | breakeven (or scratch)
+    pub start_date: Option<String>, // YYYY-MM-DD local, inclusive
+    pub end_date: Option<String>,   // YYYY-MM-DD local, inclusive
+    pub pnl_min: Option<f64>, // net, inclusive
+    pub pnl_max: Option<f64>,
+    pub rule_id: Option<String>,
+    pub rule_checked: Option<bool>, // with rule_id; defaults to true
+    pub tags: Vec<String>, // tag ids, any of
+    pub notes: Option<String>, // substring, case-insensitive
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeQueryResult {
+    pub trades: Vec<Trade>,
+    pub total: i64, // all matches, ignoring limit/offset
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeStats {
+    pub trade_count: i64,
+    pub wins: i64,
+    pub losses: i64,
+    pub breakeven: i64,
+    pub win_rate_pct: f64,
+    pub net_pnl: f64,
+    pub gross_profit: f64,
+    pub gross_loss: f64, // positive
+    pub total_fees: f64,
+    pub total_contracts: f64,
+    pub avg_win: f64,
+    pub avg_loss: f64, // negative
+    pub profit_factor: Option<f64>, // None without losing trades
+    pub expectancy: f64, // per trade
+    pub expectancy_per_contract: f64,
+    pub largest_win: f64,
+    pub largest_loss: f64, // negative
+    pub max_consecutive_wins: i64,
+    pub max_consecutive_losses: i64,
+    pub max_drawdown: f64, // positive, on closed-trade equity
+    pub max_drawdown_peak_date: Option<String>, // None when the peak is the start
+    pub max_drawdown_trough_date: Option<String>,
+}
+
+/// One point of the closed-trade equity curve. Drawdown is measured from the
+/// running high-water mark (which starts at zero) and is >= 0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub trade_id: String,
+    pub exit_time_utc: i64,
+    pub date_local: String, // YYYY-MM-DD
+    pub pnl_net: f64,
+    pub cumulative: f64,
+    pub high_water_mark: f64,
+    pub drawdown: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyEquity {
+    pub date_local: String, // YYYY-MM-DD
+    pub trade_count: i64,
+    pub pnl_net: f64,
+    pub cumulative: f64,
+    pub high_water_mark: f64,
+    pub drawdown: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityCurve {
+    pub trades: Vec<EquityPoint>,
+    pub days: Vec<DailyEquity>,
+}
+
+/// Aggregate of the trades falling into one breakdown bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakdownBucket {
+    pub key: String, // "09:30", "Fri", "ny", "5-15m"
+    pub trade_count: i64,
+    pub wins: i64,
+    pub net_pnl: f64,
+    pub win_rate_pct: f64,
+    pub expectancy: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdherencePeriod {
+    pub period: String, // "2024-W07" or "2024-02"
+    pub trade_count: i64,
+    pub followed: i64,
+    pub adherence_pct: f64,
+}
+
+/// How trades did with a checklist rule followed vs. broken.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleAdherence {
+    pub rule_id: String,
+    pub label: String,
+    pub adherence_pct: f64,
+    pub followed: BreakdownBucket, // key "followed"
+    pub broken: BreakdownBucket,   // key "broken"
+    /// Broken trades x (followed expectancy - broken expectancy); None until both sides have trades.
+    pub cost_of_breaking: Option<f64>,
+    pub periods: Vec<AdherencePeriod>,
+}
+
+/// One full-text search match. Matched terms in `snippet` are wrapped in `**`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub kind: String, // trade | journal
+    pub id: String,   // trade id or journal entry id
+    pub date_local: String, // YYYY-MM-DD
+    pub title: String, // trade symbol, or "Journal"
+    pub snippet: String,
+    pub rank: f64, // bm25, lower is better
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagPerformance {
+    pub tag: Tag,
+    pub trade_count: i64,
+    pub wins: i64,
+    pub net_pnl: f64,
+    pub win_rate_pct: f64,
+    pub expectancy: f64,
+    pub profit_factor: Option<f64>, // None without losing trades
+}
+
+/// Image attached to a trade or a journal day. The full image is fetched
+/// separately; listings carry only the thumbnail.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    pub trade_id: Option<String>,
+    pub journal_entry_id: Option<String>,
+    pub file_name: String,
+    pub mime_type: String, // image/png | image/jpeg | image/webp
+    pub width: i64,
+    pub height: i64,
+    pub size_bytes: i64,
+    pub thumbnail_base64: String, // PNG, at most 320px on the long side
+    pub created_at_utc: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentInput {
+    /// Exactly one of `trade_id` and `date_local` (journal day, YYYY-MM-DD).
+    #[serde(default)]
+    pub trade_id: Option<String>,
+    #[serde(default)]
+    pub date_local: Option<String>,
+    #[serde(default)]
+    pub file_name: String,
+    pub data_base64: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentData {
+    pub attachment: Attachment,
+    pub data_base64: String,
+}
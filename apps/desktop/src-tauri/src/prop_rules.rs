@@ -0,0 +1,323 @@
+use chrono::{Duration, TimeZone, Timelike, Utc};
+use chrono_tz::America::Chicago;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::models::{DrawdownMode, PropReport, PropRules, PropRulesInput, PropViolation};
+
+// CME Globex sessions open at 17:00 Chicago time and belong to the next day.
+const SESSION_ROLL_HOUR: u32 = 17;
+
+pub fn get_prop_rules(conn: &Connection, account_id: &str) -> anyhow::Result<Option<PropRules>> {
+    let rules = conn
+        .query_row(
+            "SELECT account_id, profit_target, max_drawdown, drawdown_mode, drawdown_lock_balance,
+                    daily_loss_limit, consistency_pct, min_trading_days, updated_at_utc
+             FROM prop_rules WHERE account_id = ?1",
+            params![account_id],
+            |row| {
+                Ok(PropRules {
+                    account_id: row.get(0)?,
+                    profit_target: row.get(1)?,
+                    max_drawdown: row.get(2)?,
+                    drawdown_mode: mode_from_db(row.get(3)?)?,
+                    drawdown_lock_balance: row.get(4)?,
+                    daily_loss_limit: row.get(5)?,
+                    consistency_pct: row.get(6)?,
+                    min_trading_days: row.get(7)?,
+                    updated_at_utc: row.get(8)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(rules)
+}
+
+pub fn set_prop_rules(conn: &Connection, account_id: &str, input: PropRulesInput) -> anyhow::Result<PropRules> {
+    validate_prop_rules(&input)?;
+    crate::accounts::get_account(conn, account_id)?;
+
+    let now = Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT INTO prop_rules (
+            account_id, profit_target, max_drawdown, drawdown_mode, drawdown_lock_balance,
+            daily_loss_limit, consistency_pct, min_trading_days, updated_at_utc
+         ) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9)
+         ON CONFLICT(account_id) DO UPDATE SET
+            profit_target=excluded.profit_target, max_drawdown=excluded.max_drawdown,
+            drawdown_mode=excluded.drawdown_mode, drawdown_lock_balance=excluded.drawdown_lock_balance,
+            daily_loss_limit=excluded.daily_loss_limit, consistency_pct=excluded.consistency_pct,
+            min_trading_days=excluded.min_trading_days, updated_at_utc=excluded.updated_at_utc",
+        params![
+            account_id,
+            input.profit_target,
+            input.max_drawdown,
+            mode_to_db(input.drawdown_mode),
+            input.drawdown_lock_balance,
+            input.daily_loss_limit,
+            input.consistency_pct,
+            input.min_trading_days,
+            now
+        ],
+    )?;
+
+    get_prop_rules(conn, account_id)?.ok_or_else(|| anyhow::anyhow!("prop rules not saved"))
+}
+
+pub fn delete_prop_rules(conn: &Connection, account_id: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM prop_rules WHERE account_id=?1", params![account_id])?;
+    Ok(())
+}
+
+fn mode_to_db(mode: DrawdownMode) -> &'static str {
+    match mode {
+        DrawdownMode::Eod => "eod",
+        DrawdownMode::Intraday => "intraday",
+        DrawdownMode::Static => "static",
+    }
+}
+
+fn mode_from_db(s: String) -> rusqlite::Result<DrawdownMode> {
+    match s.as_str() {
+        "eod" => Ok(DrawdownMode::Eod),
+        "intraday" => Ok(DrawdownMode::Intraday),
+        "static" => Ok(DrawdownMode::Static),
+        other => Err(rusqlite::Error::FromSqlConversionFailure(
+            3,
+            rusqlite::types::Type::Text,
+            format!("unknown drawdown mode: {other}").into(),
+        )),
+    }
+}
+
+/// Futures session a time belongs to, as "YYYY-MM-DD": the Chicago date, rolled
+/// to the next day from the 17:00 open.
+fn session_date(utc_ms: i64) -> String {
+    let local = Utc.timestamp_millis_opt(utc_ms).single().unwrap_or_default().with_timezone(&Chicago);
+    let mut date = local.date_naive();
+    if local.hour() >= SESSION_ROLL_HOUR {
+        date += Duration::days(1);
+    }
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn validate_prop_rules(input: &PropRulesInput) -> anyhow::Result<()> {
+    let positive = [
+        ("profit target", input.profit_target),
+        ("max drawdown", input.max_drawdown),
+        ("daily loss limit", input.daily_loss_limit),
+    ];
+    for (name, v) in positive {
+        if v.is_some_and(|v| !v.is_finite() || v <= 0.0) {
+            anyhow::bail!("{name} must be > 0");
+        }
+    }
+    if input.consistency_pct.is_some_and(|p| !(p > 0.0 && p <= 100.0)) {
+        anyhow::bail!("consistency must be between 0 and 100%");
+    }
+    if input.min_trading_days.is_some_and(|d| d < 0) {
+        anyhow::bail!("min trading days must be >= 0");
+    }
+    if input.drawdown_lock_balance.is_some() && input.max_drawdown.is_none() {
+        anyhow::bail!("drawdown lock needs a max drawdown");
+    }
+    Ok(())
+}
+
+struct ReplayTrade {
+    id: String,
+    exit_time_utc: i64,
+    pnl_net: f64,
+}
+
+/// Replay the account's trades in exit-time order against its prop rules.
+///
+/// Trading days are futures sessions (see `session_date`), whatever the display
+/// timezone. Intraday trailing follows the closed-trade balance; open-position
+/// peaks are not known.
+pub fn prop_report(conn: &Connection, account_id: &str) -> anyhow::Result<PropReport> {
+    let account = crate::accounts::get_account(conn, account_id)?;
+    let rules = get_prop_rules(conn, account_id)?.unwrap_or(PropRules {
+        account_id: account_id.to_string(),
+        profit_target: None,
+        max_drawdown: None,
+        drawdown_mode: DrawdownMode::Eod,
+        drawdown_lock_balance: None,
+        daily_loss_limit: None,
+        consistency_pct: None,
+        min_trading_days: None,
+        updated_at_utc: 0,
+    });
+
+    let mut stmt = conn.prepare(
+        "SELECT id, exit_time_utc, pnl_net FROM trades
+         WHERE account_id = ?1
+         ORDER BY exit_time_utc ASC, id ASC",
+    )?;
+    let rows = stmt.query_map(params![account_id], |row| {
+        Ok(ReplayTrade {
+            id: row.get(0)?,
+            exit_time_utc: row.get(1)?,
+            pnl_net: row.get(2)?,
+        })
+    })?;
+    let mut trades = Vec::new();
+    for r in rows {
+        trades.push(r?);
+    }
+
+    let start = account.starting_balance;
+    let trailing = rules.drawdown_mode != DrawdownMode::Static;
+    let lock = rules.drawdown_lock_balance.unwrap_or(f64::INFINITY);
+
+    let mut balance = start;
+    let mut hwm = start;
+    let mut threshold = rules.max_drawdown.map(|dd| start - dd);
+    let mut breached = false;
+    let mut violations = Vec::new();
+
+    // (date, pnl, last trade of the day)
+    let mut days: Vec<(String, f64, usize)> = Vec::new();
+    let mut day_limit_hit = false;
+
+    for (i, t) in trades.iter().enumerate() {
+        let date = session_date(t.exit_time_utc);
+        if days.last().is_none_or(|(d, _, _)| *d != date) {
+            // End-of-day trailing moves the threshold with the previous day's closing balance.
+            if rules.drawdown_mode == DrawdownMode::Eod && !days.is_empty() {
+                trail(&mut hwm, &mut threshold, balance, rules.max_drawdown, lock);
+            }
+            days.push((date.clone(), 0.0, i));
+            day_limit_hit = false;
+        }
+        let day = days.last_mut().expect("day pushed above");
+        day.1 += t.pnl_net;
+        day.2 = i;
+        let day_pnl = day.1;
+
+        balance += t.pnl_net;
+        if rules.drawdown_mode == DrawdownMode::Intraday {
+            trail(&mut hwm, &mut threshold, balance, rules.max_drawdown, lock);
+        } else if !trailing {
+            hwm = hwm.max(balance);
+        }
+
+        if let Some(th) = threshold {
+            if !breached && balance <= th {
+                breached = true;
+                violations.push(violation(
+                    "drawdown",
+                    t,
+                    &date,
+                    format!("balance {balance:.2} reached the drawdown threshold {th:.2}"),
+                ));
+            }
+        }
+        if let Some(limit) = rules.daily_loss_limit {
+            if !day_limit_hit && day_pnl <= -limit {
+                day_limit_hit = true;
+                violations.push(violation(
+                    "daily_loss",
+                    t,
+                    &date,
+                    format!("day loss {:.2} reached the daily loss limit {limit:.2}", -day_pnl),
+                ));
+            }
+        }
+    }
+
+    // The last session only closes once the next one has opened.
+    let today = session_date(Utc::now().timestamp_millis());
+    if rules.drawdown_mode == DrawdownMode::Eod && days.last().is_some_and(|(d, _, _)| *d != today) {
+        trail(&mut hwm, &mut threshold, balance, rules.max_drawdown, lock);
+    }
+
+    let profit = balance - start;
+    let best = days
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .filter(|(_, pnl, _)| *pnl > 0.0);
+    let best_day_pnl = best.map_or(0.0, |(_, pnl, _)| *pnl);
+    let best_day_share_pct = (profit > 0.0).then(|| best_day_pnl / profit * 100.0);
+
+    let target_reached = rules.profit_target.is_none_or(|target| profit >= target);
+    // Consistency is judged when the account would otherwise pass.
+    if let (Some(max_pct), Some(share), Some((date, _, last))) = (rules.consistency_pct, best_day_share_pct, best) {
+        if target_reached && share > max_pct {
+            violations.push(violation(
+                "consistency",
+                &trades[*last],
+                date,
+                format!("best day is {share:.1}% of total profit (max {max_pct:.1}%)"),
+            ));
+        }
+    }
+
+    let today_pnl = days.iter().find(|(d, _, _)| *d == today).map_or(0.0, |(_, pnl, _)| *pnl);
+    let days_traded = days.len() as i64;
+    let enough_days = rules.min_trading_days.is_none_or(|min| days_traded >= min);
+
+    Ok(PropReport {
+        account_id: account_id.to_string(),
+        starting_balance: start,
+        balance,
+        high_water_mark: hwm,
+        drawdown_threshold: threshold,
+        drawdown_buffer: threshold.map(|th| balance - th),
+        daily_loss_remaining: rules.daily_loss_limit.map(|limit| (limit + today_pnl.min(0.0)).max(0.0)),
+        days_traded,
+        min_trading_days: rules.min_trading_days,
+        profit,
+        profit_target: rules.profit_target,
+        target_progress_pct: rules.profit_target.map(|target| (profit / target * 100.0).max(0.0)),
+        best_day_pnl,
+        best_day_share_pct,
+        passed: rules.profit_target.is_some() && target_reached && enough_days && violations.is_empty(),
+        violations,
+    })
+}
+
+/// Raise the high-water mark and move the threshold up behind it, never down.
+fn trail(hwm: &mut f64, threshold: &mut Option<f64>, balance: f64, max_drawdown: Option<f64>, lock: f64) {
+    *hwm = hwm.max(balance);
+    if let (Some(th), Some(dd)) = (threshold.as_mut(), max_drawdown) {
+        *th = th.max((*hwm - dd).min(lock));
+    }
+}
+
+fn violation(rule: &str, t: &ReplayTrade, date: &str, message: String) -> PropViolation {
+    PropViolation {
+        rule: rule.to_string(),
+        trade_id: t.id.clone(),
+        exit_time_utc: t.exit_time_utc,
+        session_date: date.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(rfc3339: &str) -> i64 {
+        chrono::DateTime::parse_from_rfc3339(rfc3339).unwrap().timestamp_millis()
+    }
+
+    #[test]
+    fn sessions_roll_at_five_pm_chicago() {
+        // CST (UTC-6)
+        assert_eq!(session_date(ms("2024-01-04T22:59:59Z")), "2024-01-04");
+        assert_eq!(session_date(ms("2024-01-04T23:00:00Z")), "2024-01-05");
+        assert_eq!(session_date(ms("2024-01-05T05:30:00Z")), "2024-01-05");
+        // CDT (UTC-5)
+        assert_eq!(session_date(ms("2024-07-01T21:59:00Z")), "2024-07-01");
+        assert_eq!(session_date(ms("2024-07-01T22:00:00Z")), "2024-07-02");
+    }
+
+    #[test]
+    fn drawdown_mode_round_trips_through_the_db_text() {
+        for mode in [DrawdownMode::Eod, DrawdownMode::Intraday, DrawdownMode::Static] {
+            assert_eq!(mode_from_db(mode_to_db(mode).to_string()).unwrap(), mode);
+        }
+        assert!(mode_from_db("weekly".to_string()).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::CipherParams;
+use crate::db::DbState;
+
+// A database with a recovery key is encrypted with a random data key rather
+// than the passphrase. The data key is stored twice beside the config, once
+// wrapped (AES-256-GCM) under a key derived from the passphrase and once under
+// one derived from the recovery key, so either secret can unlock it.
+
+const DATA_KEY_LEN: usize = 32;
+const RECOVERY_KEY_LEN: usize = 20; // 160 bits, 32 base32 characters
+const PASSPHRASE_KDF_ITER: u32 = 256_000;
+// The recovery key is random, so stretching adds little; keep unlocking fast.
+const RECOVERY_KDF_ITER: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotKind {
+    /// Wrapped with the user's passphrase.
+    Passphrase,
+    /// Wrapped with the generated recovery key.
+    Recovery,
+}
+
+impl SlotKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            SlotKind::Passphrase => "passphrase",
+            SlotKind::Recovery => "recovery",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeySlot {
+    kind: SlotKind,
+    salt: String,
+    kdf_iter: u32,
+    nonce: String,
+    wrapped_key: String,
+}
+
+/// Contents of `ftjournal.keys.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyFile {
+    version: u32,
+    slots: Vec<KeySlot>,
+}
+
+/// Path of the key file for a config file.
+pub fn key_file_path(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("ftjournal.keys.json")
+}
+
+pub fn load_key_file(path: &Path) -> anyhow::Result<Option<KeyFile>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let file: KeyFile = serde_json::from_str(&raw).context("parse key file")?;
+    if file.version != 1 {
+        anyhow::bail!("unsupported key file version {}", file.version);
+    }
+    Ok(Some(file))
+}
+
+pub fn save_key_file(path: &Path, file: &KeyFile) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let raw = serde_json::to_string_pretty(file)?;
+    let tmp_path = path.with_extension("json.tmp");
+    let mut tmp = std::fs::File::create(&tmp_path)?;
+    tmp.write_all(raw.as_bytes())?;
+    tmp.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// A new data key wrapped under `passphrase` and a fresh recovery key.
+/// Returns the key file, the SQLCipher key for the database and the recovery
+/// key to show the user once.
+pub fn generate_keys(passphrase: &str) -> anyhow::Result<(KeyFile, String, String)> {
+    let mut data_key = [0u8; DATA_KEY_LEN];
+    OsRng.fill_bytes(&mut data_key);
+    let mut recovery = [0u8; RECOVERY_KEY_LEN];
+    OsRng.fill_bytes(&mut recovery);
+    let recovery_key = format_recovery_key(&recovery);
+
+    let file = KeyFile {
+        version: 1,
+        slots: vec![
+            wrap(SlotKind::Passphrase, passphrase, &data_key)?,
+            wrap(SlotKind::Recovery, &normalize_recovery_key(&recovery_key), &data_key)?,
+        ],
+    };
+    Ok((file, sqlcipher_key(&data_key), recovery_key))
+}
+
+/// SQLCipher key for the database, from either the passphrase or the recovery key.
+pub fn unwrap_db_key(file: &KeyFile, secret: &str) -> anyhow::Result<String> {
+    let data_key = unwrap_any(file, secret)?;
+    Ok(sqlcipher_key(&data_key))
+}
+
+/// Re-wrap the data key under `new_passphrase`. `secret` may be the current
+/// passphrase or the recovery key; the recovery slot is left unchanged.
+pub fn rewrap_passphrase(file: &mut KeyFile, secret: &str, new_passphrase: &str) -> anyhow::Result<()> {
+    let data_key = unwrap_any(file, secret)?;
+    let slot = wrap(SlotKind::Passphrase, new_passphrase, &data_key)?;
+    file.slots.retain(|s| s.kind != SlotKind::Passphrase);
+    file.slots.insert(0, slot);
+    Ok(())
+}
+
+/// The key to hand to SQLCipher: the unwrapped data key when the database has
+/// a key file, otherwise the secret itself (a plain passphrase database).
+pub fn resolve_db_key(key_path: &Path, secret: &str) -> anyhow::Result<String> {
+    match load_key_file(key_path)? {
+        Some(file) => unwrap_db_key(&file, secret),
+        None => Ok(secret.to_string()),
+    }
+}
+
+/// Create a new encrypted database keyed by a random data key and write its
+/// key file. Returns the recovery key.
+pub fn create_with_recovery_key(
+    state: &DbState,
+    db_path: &Path,
+    key_path: &Path,
+    passphrase: &str,
+    cipher: &CipherParams,
+) -> anyhow::Result<String> {
+    if passphrase.is_empty() {
+        anyhow::bail!("passphrase required for encrypted database");
+    }
+    let (file, db_key, recovery_key) = generate_keys(passphrase)?;
+    state.create_new(db_path, true, Some(&db_key), cipher)?;
+
+    // Without its key file the new database could never be opened again.
+    if let Err(e) = save_key_file(key_path, &file) {
+        state.close();
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", db_path.to_string_lossy(), suffix));
+        }
+        return Err(e);
+    }
+    Ok(recovery_key)
+}
+
+fn unwrap_any(file: &KeyFile, secret: &str) -> anyhow::Result<Vec<u8>> {
+    for slot in &file.slots {
+        let candidate = match slot.kind {
+            SlotKind::Passphrase => secret.to_string(),
+            SlotKind::Recovery => normalize_recovery_key(secret),
+        };
+        if let Some(key) = unwrap(slot, &candidate)? {
+            return Ok(key);
+        }
+    }
+    anyhow::bail!("wrong passphrase or recovery key")
+}
+
+fn wrap(kind: SlotKind, secret: &str, data_key: &[u8]) -> anyhow::Result<KeySlot> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let kdf_iter = match kind {
+        SlotKind::Passphrase => PASSPHRASE_KDF_ITER,
+        SlotKind::Recovery => RECOVERY_KDF_ITER,
+    };
+
+    let cipher = Aes256Gcm::new_from_slice(&derive(secret, &salt, kdf_iter))?;
+    let wrapped = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: data_key, aad: kind.as_str().as_bytes() })
+        .map_err(|_| anyhow::anyhow!("failed to wrap database key"))?;
+
+    Ok(KeySlot {
+        kind,
+        salt: BASE64.encode(salt),
+        kdf_iter,
+        nonce: BASE64.encode(nonce),
+        wrapped_key: BASE64.encode(wrapped),
+    })
+}
+
+// `None` when the secret is wrong for this slot.
+fn unwrap(slot: &KeySlot, secret: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    let salt = BASE64.decode(&slot.salt).context("corrupt key file")?;
+    let nonce = BASE64.decode(&slot.nonce).context("corrupt key file")?;
+    let wrapped = BASE64.decode(&slot.wrapped_key).context("corrupt key file")?;
+    if nonce.len() != 12 {
+        anyhow::bail!("corrupt key file");
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(&derive(secret, &salt, slot.kdf_iter))?;
+    Ok(cipher
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &wrapped, aad: slot.kind.as_str().as_bytes() })
+        .ok())
+}
+
+fn derive(secret: &str, salt: &[u8], kdf_iter: u32) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha512>(secret.as_bytes(), salt, kdf_iter, &mut out);
+    out
+}
+
+/// SQLCipher raw-key syntax; skips its own KDF since the key is already random.
+fn sqlcipher_key(data_key: &[u8]) -> String {
+    let hex: String = data_key.iter().map(|b| format!("{b:02X}")).collect();
+    format!("x'{hex}'")
+}
+
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Base32 in dash-separated groups of four: "ABCD-EFGH-...".
+fn format_recovery_key(bytes: &[u8]) -> String {
+    let mut chars = Vec::new();
+    let (mut buf, mut bits) = (0u32, 0);
+    for b in bytes {
+        buf = (buf << 8) | u32::from(*b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            chars.push(BASE32[((buf >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        chars.push(BASE32[((buf << (5 - bits)) & 31) as usize] as char);
+    }
+    chars.chunks(4).map(|c| c.iter().collect::<String>()).collect::<Vec<_>>().join("-")
+}
+
+// Accept the recovery key however it was copied down: any case, with or without dashes.
+fn normalize_recovery_key(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_round_trip() {
+        let data_key = [7u8; DATA_KEY_LEN];
+        let slot = wrap(SlotKind::Passphrase, "correct horse", &data_key).unwrap();
+        assert_eq!(unwrap(&slot, "correct horse").unwrap(), Some(data_key.to_vec()));
+    }
+
+    #[test]
+    fn wrong_secret_is_rejected() {
+        let (file, _, _) = generate_keys("pw").unwrap();
+        assert!(unwrap_db_key(&file, "not the passphrase").is_err());
+        assert!(unwrap_db_key(&file, "AAAA-AAAA-AAAA-AAAA-AAAA-AAAA-AAAA-AAAA").is_err());
+    }
+
+    #[test]
+    fn recovery_key_ignores_dashes_and_case() {
+        let (file, db_key, recovery_key) = generate_keys("pw").unwrap();
+        assert_eq!(unwrap_db_key(&file, &recovery_key).unwrap(), db_key);
+        let sloppy = recovery_key.replace('-', "").to_lowercase();
+        assert_eq!(unwrap_db_key(&file, &sloppy).unwrap(), db_key);
+    }
+
+    #[test]
+    fn rewrap_keeps_recovery_slot() {
+        let (mut file, db_key, recovery_key) = generate_keys("old").unwrap();
+        rewrap_passphrase(&mut file, "old", "new").unwrap();
+        assert!(unwrap_db_key(&file, "old").is_err());
+        assert_eq!(unwrap_db_key(&file, "new").unwrap(), db_key);
+        assert_eq!(unwrap_db_key(&file, &recovery_key).unwrap(), db_key);
+    }
+
+    #[test]
+    fn recovery_key_format() {
+        let key = format_recovery_key(&[0xFF; RECOVERY_KEY_LEN]);
+        // 32 base32 characters in eight groups of four.
+        assert_eq!(key.len(), 39);
+        assert_eq!(key.split('-').count(), 8);
+        assert!(key.split('-').all(|g| g == "7777"));
+        assert_eq!(format_recovery_key(&[0; 5]), "AAAA-AAAA");
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, CipherParams};
+use crate::db::DbState;
+
+/// Change the passphrase of the configured database, encrypt a plaintext one
+/// (`new_passphrase` set on an unencrypted DB) or decrypt to plaintext
+/// (`new_passphrase` is `None`). The new file uses `cipher`, so passing the
+/// current passphrase twice re-encrypts with upgraded settings.
+///
+/// The data is copied with `sqlcipher_export` into a fresh file, which then
+/// replaces the original. Until the new file and the config are both in place
+/// the old database is kept, and any failure puts it back and reopens it. The
+/// config marks the swap as pending while it runs; see `recover`.
+pub fn change_encryption(
+    state: &DbState,
+    cfg_path: &Path,
+    current_passphrase: Option<&str>,
+    new_passphrase: Option<&str>,
+    cipher: &CipherParams,
+) -> anyhow::Result<()> {
+    cipher.validate()?;
+    let cfg = config::load_config(&cfg_path.to_path_buf())?.context("App not initialized")?;
+    let db_path = PathBuf::from(&cfg.db_path);
+
+    let new_passphrase = new_passphrase.filter(|p| !p.is_empty());
+    let encrypt = new_passphrase.is_some();
+    if !cfg.encrypted && !encrypt {
+        anyhow::bail!("database is not encrypted");
+    }
+    if cfg.pending_rekey.is_some() {
+        anyhow::bail!("an earlier re-encryption did not finish; restart the app to recover it");
+    }
+    let current_passphrase = if cfg.encrypted {
+        let p = current_passphrase.context("current passphrase required")?;
+        crate::db::verify_key(&db_path, true, Some(p), &cfg.cipher).map_err(|_| anyhow::anyhow!("current passphrase is incorrect"))?;
+        Some(p)
+    } else {
+        crate::db::verify_key(&db_path, false, None, &cfg.cipher)?;
+        None
+    };
+
+    // With no swap pending and the live database checked above, leftovers
+    // from earlier runs are no longer the only good copy of anything.
+    let tmp_path = sibling(&db_path, ".rekey");
+    let bak_path = sibling(&db_path, ".bak");
+    for p in [&tmp_path, &bak_path] {
+        if p.exists() {
+            std::fs::remove_file(p).with_context(|| format!("remove stale {}", p.display()))?;
+        }
+    }
+
+    let new_cfg = config::AppConfig {
+        encrypted: encrypt,
+        cipher: cipher.clone(),
+        ..cfg.clone()
+    };
+
+    // The connection lock is held from the export to the reopen, so nothing
+    // can be written to the old file after it was copied.
+    state.with_conn_slot(|slot| {
+        // 1. Copy into the new file.
+        let conn = slot.as_ref().context("database is locked")?;
+        if let Err(e) = crate::db::export_copy(conn, &tmp_path, new_passphrase, cipher) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+
+        // 2. Check the copy opens with its new key before touching the original.
+        *slot = None;
+        if let Err(e) = crate::db::verify_key(&tmp_path, encrypt, new_passphrase, cipher) {
+            let _ = std::fs::remove_file(&tmp_path);
+            *slot = Some(crate::db::open_connection(&db_path, cfg.encrypted, current_passphrase, &cfg.cipher)?);
+            return Err(e.context("re-encrypted copy failed verification"));
+        }
+
+        // 3. Swap the files, record the new mode and reopen.
+        let swapped = (|| -> anyhow::Result<()> {
+            let pending = config::AppConfig {
+                pending_rekey: Some(config::PendingRekey { encrypted: encrypt, cipher: cipher.clone() }),
+                ..cfg.clone()
+            };
+            config::save_config(&cfg_path.to_path_buf(), &pending)?;
+            std::fs::rename(&db_path, &bak_path).with_context(|| format!("move {} aside", db_path.display()))?;
+            remove_sidecars(&db_path);
+            std::fs::rename(&tmp_path, &db_path).with_context(|| format!("replace {}", db_path.display()))?;
+            config::save_config(&cfg_path.to_path_buf(), &new_cfg)?;
+            *slot = Some(crate::db::open_connection(&db_path, encrypt, new_passphrase, cipher)?);
+            Ok(())
+        })();
+
+        if let Err(e) = swapped {
+            *slot = None;
+            if bak_path.exists() {
+                remove_sidecars(&db_path);
+                let _ = std::fs::remove_file(&db_path);
+                std::fs::rename(&bak_path, &db_path).with_context(|| format!("restore {}", db_path.display()))?;
+            }
+            let _ = std::fs::remove_file(&tmp_path);
+            config::save_config(&cfg_path.to_path_buf(), &cfg)?;
+            *slot = Some(crate::db::open_connection(&db_path, cfg.encrypted, current_passphrase, &cfg.cipher)?);
+            return Err(e);
+        }
+        Ok(())
+    })?;
+
+    state.set_opened(cipher, new_passphrase);
+    state.configure(new_cfg.db_path, encrypt);
+    let _ = std::fs::remove_file(&bak_path);
+    Ok(())
+}
+
+/// Finish or undo a re-encryption that a crash cut short while it swapped
+/// files. Runs before the configured database is opened. The `.bak` file is
+/// always left in place; the next `change_encryption` clears it once the live
+/// database has been checked.
+pub fn recover(cfg_path: &Path) -> anyhow::Result<()> {
+    let Some(cfg) = config::load_config(&cfg_path.to_path_buf())? else {
+        return Ok(());
+    };
+    let Some(pending) = cfg.pending_rekey.clone() else {
+        return Ok(());
+    };
+    let db_path = PathBuf::from(&cfg.db_path);
+    let tmp_path = sibling(&db_path, ".rekey");
+    let bak_path = sibling(&db_path, ".bak");
+
+    // The swap moves the database to .bak and then the verified copy into its
+    // place; a rollback moves .bak back. Work out which side of that we are on.
+    let finished = if db_path.exists() {
+        // The copy is in place once it is gone and the original sits in .bak.
+        !tmp_path.exists() && bak_path.exists()
+    } else if tmp_path.exists() {
+        remove_sidecars(&db_path);
+        std::fs::rename(&tmp_path, &db_path).with_context(|| format!("move {} into place", tmp_path.display()))?;
+        true
+    } else if bak_path.exists() {
+        remove_sidecars(&db_path);
+        std::fs::rename(&bak_path, &db_path).with_context(|| format!("restore {}", db_path.display()))?;
+        false
+    } else {
+        anyhow::bail!("database file {} is missing", db_path.display());
+    };
+
+    let cfg = if finished {
+        config::AppConfig {
+            encrypted: pending.encrypted,
+            cipher: pending.cipher,
+            pending_rekey: None,
+            ..cfg
+        }
+    } else {
+        if tmp_path.exists() {
+            std::fs::remove_file(&tmp_path).with_context(|| format!("remove {}", tmp_path.display()))?;
+        }
+        config::AppConfig { pending_rekey: None, ..cfg }
+    };
+    config::save_config(&cfg_path.to_path_buf(), &cfg)
+}
+
+fn sibling(db_path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", db_path.to_string_lossy(), suffix))
+}
+
+// WAL/SHM files belong to the file they were created with; never let them leak onto a replacement.
+fn remove_sidecars(db_path: &Path) {
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(sibling(db_path, suffix));
+    }
+}
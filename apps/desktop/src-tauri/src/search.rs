@@ -0,0 +1,68 @@
+use rusqlite::{params, Connection};
+
+use crate::journal::{local_date_str, parse_tz};
+use crate::models::SearchHit;
+
+/// Search trade notes/symbols and journal entries, best matches first.
+///
+/// Every word of `query` must appear (as a prefix); FTS5 operators in the
+/// input are treated as plain text.
+pub fn search(conn: &Connection, tz_name: &str, query: &str, limit: i64) -> anyhow::Result<Vec<SearchHit>> {
+    let tz = parse_tz(tz_name)?;
+    let Some(match_expr) = match_expression(query) else {
+        return Ok(Vec::new());
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT kind, id, date_local, exit_time_utc, title, snippet, rank FROM (
+            SELECT 'trade' AS kind, t.id AS id, NULL AS date_local, t.exit_time_utc AS exit_time_utc,
+                   t.symbol AS title,
+                   snippet(trades_fts, 1, '**', '**', '…', 12) AS snippet,
+                   bm25(trades_fts) AS rank
+            FROM trades_fts
+            JOIN trades_fts_map m ON m.fts_rowid = trades_fts.rowid
+            JOIN trades t ON t.id = m.trade_id
+            WHERE trades_fts MATCH ?1
+            UNION ALL
+            SELECT 'journal', e.id, e.date_local, NULL, 'Journal',
+                   snippet(journal_fts, 0, '**', '**', '…', 12),
+                   bm25(journal_fts)
+            FROM journal_fts
+            JOIN journal_fts_map m ON m.fts_rowid = journal_fts.rowid
+            JOIN journal_entries e ON e.id = m.entry_id
+            WHERE journal_fts MATCH ?1
+         )
+         ORDER BY rank ASC
+         LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![match_expr, limit], |row| {
+        let date_local: Option<String> = row.get(2)?;
+        let exit_time_utc: Option<i64> = row.get(3)?;
+        Ok(SearchHit {
+            kind: row.get(0)?,
+            id: row.get(1)?,
+            date_local: date_local.unwrap_or_else(|| local_date_str(tz, exit_time_utc.unwrap_or_default())),
+            title: row.get(4)?,
+            snippet: row.get(5)?,
+            rank: row.get(6)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+/// Quote each word so user input can never be parsed as FTS5 syntax.
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|w| format!("\"{}\"*", w.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+    Some(terms.join(" "))
+}
@@ -0,0 +1,165 @@
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::models::{Tag, TagGroup, TagInput};
+
+fn tag_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        group_id: row.get(1)?,
+        name: row.get(2)?,
+        color: row.get(3)?,
+    })
+}
+
+pub fn list_tag_groups(conn: &Connection) -> anyhow::Result<Vec<TagGroup>> {
+    let mut stmt = conn.prepare("SELECT id, name, sort_order FROM tag_groups ORDER BY sort_order ASC, name ASC")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TagGroup {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            sort_order: row.get(2)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+pub fn upsert_tag_group(conn: &Connection, group: &TagGroup) -> anyhow::Result<()> {
+    if group.id.trim().is_empty() || group.name.trim().is_empty() {
+        anyhow::bail!("tag group id and name are required");
+    }
+    conn.execute(
+        "INSERT INTO tag_groups (id, name, sort_order) VALUES (?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET name=excluded.name, sort_order=excluded.sort_order",
+        params![group.id.trim(), group.name.trim(), group.sort_order],
+    )?;
+    Ok(())
+}
+
+/// Tags of a deleted group are kept and become ungrouped.
+pub fn delete_tag_group(conn: &Connection, id: &str) -> anyhow::Result<()> {
+    let clash: Option<String> = conn
+        .query_row(
+            "SELECT t.name FROM tags t JOIN tags u ON u.group_id IS NULL AND u.name = t.name
+             WHERE t.group_id = ?1 LIMIT 1",
+            params![id],
+            |r| r.get(0),
+        )
+        .optional()?;
+    if let Some(name) = clash {
+        anyhow::bail!("an ungrouped tag named \"{name}\" already exists; rename one of them first");
+    }
+    conn.execute("DELETE FROM tag_groups WHERE id=?1", params![id])?;
+    Ok(())
+}
+
+pub fn list_tags(conn: &Connection) -> anyhow::Result<Vec<Tag>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.group_id, t.name, t.color
+         FROM tags t
+         LEFT JOIN tag_groups g ON g.id = t.group_id
+         ORDER BY g.sort_order IS NULL, g.sort_order ASC, t.name ASC",
+    )?;
+    let rows = stmt.query_map([], tag_from_row)?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+pub fn get_tag(conn: &Connection, id: &str) -> anyhow::Result<Tag> {
+    conn.query_row("SELECT id, group_id, name, color FROM tags WHERE id = ?1", params![id], tag_from_row)
+        .optional()?
+        .with_context(|| format!("unknown tag: {id}"))
+}
+
+pub fn create_tag(conn: &Connection, input: TagInput) -> anyhow::Result<Tag> {
+    validate_tag(conn, None, &input)?;
+
+    let id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT INTO tags (id, group_id, name, color, created_at_utc) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, input.group_id, input.name.trim(), input.color.trim(), now],
+    )?;
+
+    get_tag(conn, &id)
+}
+
+pub fn update_tag(conn: &Connection, id: &str, input: TagInput) -> anyhow::Result<Tag> {
+    validate_tag(conn, Some(id), &input)?;
+
+    let n = conn.execute(
+        "UPDATE tags SET group_id=?2, name=?3, color=?4 WHERE id=?1",
+        params![id, input.group_id, input.name.trim(), input.color.trim()],
+    )?;
+    if n == 0 {
+        anyhow::bail!("unknown tag: {id}");
+    }
+
+    get_tag(conn, id)
+}
+
+pub fn delete_tag(conn: &Connection, id: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM tags WHERE id=?1", params![id])?;
+    Ok(())
+}
+
+fn validate_tag(conn: &Connection, id: Option<&str>, input: &TagInput) -> anyhow::Result<()> {
+    if input.name.trim().is_empty() {
+        anyhow::bail!("tag name is required");
+    }
+    if let Some(group_id) = input.group_id.as_deref() {
+        let n: i64 = conn.query_row("SELECT COUNT(*) FROM tag_groups WHERE id = ?1", params![group_id], |r| r.get(0))?;
+        if n == 0 {
+            anyhow::bail!("unknown tag group: {group_id}");
+        }
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM tags WHERE group_id IS ?1 AND name = ?2 AND id IS NOT ?3)",
+        params![input.group_id, input.name.trim(), id],
+        |r| r.get(0),
+    )?;
+    if taken {
+        anyhow::bail!("tag \"{}\" already exists in this group", input.name.trim());
+    }
+    Ok(())
+}
+
+pub fn tags_for_trade(conn: &Connection, trade_id: &str) -> anyhow::Result<Vec<Tag>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.group_id, t.name, t.color
+         FROM trade_tags tt
+         JOIN tags t ON t.id = tt.tag_id
+         WHERE tt.trade_id = ?1
+         ORDER BY t.name ASC",
+    )?;
+    let rows = stmt.query_map(params![trade_id], tag_from_row)?;
+
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
+/// Replace the trade's tags with `tag_ids`.
+pub fn set_trade_tags(conn: &Connection, trade_id: &str, tag_ids: &[String]) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM trade_tags WHERE trade_id = ?1", params![trade_id])?;
+    for tag_id in tag_ids {
+        get_tag(conn, tag_id)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO trade_tags (trade_id, tag_id) VALUES (?1, ?2)",
+            params![trade_id, tag_id],
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use rusqlite::types::Value;
+
+use crate::journal::{parse_tz, utc_range_for_local_date};
+use crate::models::TradeFilter;
+
+/// SQL `WHERE` clause (without the keyword) and its positional parameters for
+/// a trade filter. Always valid SQL, "1=1" when nothing is set.
+///
+/// Date bounds are inclusive local dates in `tz_name`, as in the journal.
+pub(crate) fn filter_sql(tz_name: &str, filter: &TradeFilter) -> anyhow::Result<(String, Vec<Value>)> {
+    let tz = parse_tz(tz_name)?;
+    let mut clauses: Vec<String> = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    let set = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+
+    if let Some(account_id) = set(&filter.account_id) {
+        clauses.push("account_id = ?".to_string());
+        values.push(Value::Text(account_id));
+    }
+    if let Some(symbol) = set(&filter.symbol) {
+        clauses.push("instr(lower(symbol), lower(?)) > 0".to_string());
+        values.push(Value::Text(symbol));
+    }
+    let symbols: Vec<&str> = filter.symbols.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+    if !symbols.is_empty() {
+        clauses.push(any_of("upper(symbol)", symbols.len()));
+        values.extend(symbols.iter().map(|s| Value::Text(s.to_uppercase())));
+    }
+    if let Some(instrument) = set(&filter.instrument) {
+        clauses.push("instrument = upper(?)".to_string());
+        values.push(Value::Text(instrument));
+    }
+    if let Some(session) = set(&filter.session) {
+        clauses.push("session = ?".to_string());
+        values.push(Value::Text(session));
+    }
+    let sessions: Vec<&str> = filter.sessions.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+    if !sessions.is_empty() {
+        clauses.push(any_of("session", sessions.len()));
+        values.extend(sessions.iter().map(|s| Value::Text(s.to_string())));
+    }
+    if let Some(side) = set(&filter.side) {
+        clauses.push("side = lower(?)".to_string());
+        values.push(Value::Text(side));
+    }
+    match set(&filter.outcome).as_deref() {
+        None => {}
+        Some("win") => clauses.push("pnl_net > 0".to_string()),
+        Some("loss") => clauses.push("pnl_net < 0".to_string()),
+        Some("breakeven" | "scratch") => clauses.push("pnl_net = 0".to_string()),
+        Some(other) => anyhow::bail!("unknown outcome: {other}"),
+    }
+    if let Some(start) = set(&filter.start_date) {
+        let (start_ms, _) = utc_range_for_local_date(tz, &start)?;
+        clauses.push("exit_time_utc >= ?".to_string());
+        values.push(Value::Integer(start_ms));
+    }
+    if let Some(end) = set(&filter.end_date) {
+        let (_, end_ms) = utc_range_for_local_date(tz, &end)?;
+        clauses.push("exit_time_utc < ?".to_string());
+        values.push(Value::Integer(end_ms));
+    }
+    if let Some(min) = filter.pnl_min {
+        clauses.push("pnl_net >= ?".to_string());
+        values.push(Value::Real(min));
+    }
+    if let Some(max) = filter.pnl_max {
+        clauses.push("pnl_net <= ?".to_string());
+        values.push(Value::Real(max));
+    }
+    if let Some(rule_id) = set(&filter.rule_id) {
+        clauses.push(
+            "EXISTS (SELECT 1 FROM trade_rules fr WHERE fr.trade_id = trades.id AND fr.rule_id = ? AND fr.checked = ?)"
+                .to_string(),
+        );
+        values.push(Value::Text(rule_id));
+        values.push(Value::Integer(i64::from(filter.rule_checked.unwrap_or(true))));
+    } else if filter.rule_checked.is_some() {
+        anyhow::bail!("rule_checked needs a rule_id");
+    }
+    let tags: Vec<&str> = filter.tags.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+    if !tags.is_empty() {
+        clauses.push(format!(
+            "EXISTS (SELECT 1 FROM trade_tags ft WHERE ft.trade_id = trades.id AND {})",
+            any_of("ft.tag_id", tags.len())
+        ));
+        values.extend(tags.iter().map(|s| Value::Text(s.to_string())));
+    }
+    if let Some(notes) = set(&filter.notes) {
+        clauses.push("instr(lower(notes), lower(?)) > 0".to_string());
+        values.push(Value::Text(notes));
+    }
+
+    if clauses.is_empty() {
+        return Ok(("1=1".to_string(), values));
+    }
+    Ok((clauses.join(" AND "), values))
+}
+
+fn any_of(column: &str, n: usize) -> String {
+    format!("{column} IN ({})", vec!["?"; n].join(", "))
+}
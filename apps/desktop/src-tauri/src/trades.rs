@@ -1,39 +1,52 @@
 use anyhow::Context;
-use rusqlite::{params, Connection};
+use rusqlite::{params, params_from_iter, Connection};
 use uuid::Uuid;
 
-use crate::models::{Rule, Trade, TradeInput, TradeWithRules};
+use crate::instruments::instrument_for_trade;
+use crate::models::{Instrument, Rule, Trade, TradeFilter, TradeInput, TradeQueryResult, TradeWithRules};
 
-pub fn list_trades(conn: &Connection, limit: i64, offset: i64) -> anyhow::Result<Vec<Trade>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, market, symbol, side, qty, entry_time_utc, exit_time_utc, timezone, session, pnl_amount,
-                pnl_includes_fees, fees, pnl_net, pnl_gross, notes, created_at_utc, updated_at_utc
+const TRADE_COLUMNS: &str = "id, market, symbol, side, qty, entry_time_utc, exit_time_utc, timezone, session, pnl_amount,
+    pnl_includes_fees, fees, pnl_net, pnl_gross, notes, instrument, entry_price, exit_price, pnl_computed, pnl_mismatch,
+    account_id, created_at_utc, updated_at_utc";
+
+fn trade_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Trade> {
+    Ok(Trade {
+        id: row.get(0)?,
+        market: row.get(1)?,
+        symbol: row.get(2)?,
+        side: row.get(3)?,
+        qty: row.get(4)?,
+        entry_time_utc: row.get(5)?,
+        exit_time_utc: row.get(6)?,
+        timezone: row.get(7)?,
+        session: row.get(8)?,
+        pnl_amount: row.get(9)?,
+        pnl_includes_fees: row.get(10)?,
+        fees: row.get(11)?,
+        pnl_net: row.get(12)?,
+        pnl_gross: row.get(13)?,
+        notes: row.get(14)?,
+        instrument: row.get(15)?,
+        entry_price: row.get(16)?,
+        exit_price: row.get(17)?,
+        pnl_computed: row.get(18)?,
+        pnl_mismatch: row.get(19)?,
+        account_id: row.get(20)?,
+        created_at_utc: row.get(21)?,
+        updated_at_utc: row.get(22)?,
+    })
+}
+
+pub fn list_trades(conn: &Connection, limit: i64, offset: i64, account_id: Option<&str>) -> anyhow::Result<Vec<Trade>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {TRADE_COLUMNS}
          FROM trades
+         WHERE (?3 IS NULL OR account_id = ?3)
          ORDER BY exit_time_utc DESC
-         LIMIT ?1 OFFSET ?2",
-    )?;
+         LIMIT ?1 OFFSET ?2"
+    ))?;
 
-    let rows = stmt.query_map(params![limit, offset], |row| {
-        Ok(Trade {
-            id: row.get(0)?,
-            market: row.get(1)?,
-            symbol: row.get(2)?,
-            side: row.get(3)?,
-            qty: row.get(4)?,
-            entry_time_utc: row.get(5)?,
-            exit_time_utc: row.get(6)?,
-            timezone: row.get(7)?,
-            session: row.get(8)?,
-            pnl_amount: row.get(9)?,
-            pnl_includes_fees: row.get(10)?,
-            fees: row.get(11)?,
-            pnl_net: row.get(12)?,
-            pnl_gross: row.get(13)?,
-            notes: row.get(14)?,
-            created_at_utc: row.get(15)?,
-            updated_at_utc: row.get(16)?,
-        })
-    })?;
+    let rows = stmt.query_map(params![limit, offset, account_id], trade_from_row)?;
 
     let mut out = Vec::new();
     for r in rows {
@@ -42,34 +55,74 @@ pub fn list_trades(conn: &Connection, limit: i64, offset: i64) -> anyhow::Result
     Ok(out)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSort {
+    #[default]
+    ExitTime,
+    EntryTime,
+    PnlNet,
+    Symbol,
+    Qty,
+    HoldDuration,
+}
+
+impl TradeSort {
+    fn column(self) -> &'static str {
+        match self {
+            TradeSort::ExitTime => "exit_time_utc",
+            TradeSort::EntryTime => "entry_time_utc",
+            TradeSort::PnlNet => "pnl_net",
+            TradeSort::Symbol => "symbol COLLATE NOCASE",
+            TradeSort::Qty => "qty",
+            TradeSort::HoldDuration => "(exit_time_utc - entry_time_utc)",
+        }
+    }
+}
+
+/// One page of the trades matching `filter`, plus how many match in total.
+pub fn query_trades(
+    conn: &Connection,
+    tz_name: &str,
+    filter: &TradeFilter,
+    sort: TradeSort,
+    descending: bool,
+    limit: i64,
+    offset: i64,
+) -> anyhow::Result<TradeQueryResult> {
+    let (where_sql, values) = crate::trade_filter::filter_sql(tz_name, filter)?;
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM trades WHERE {where_sql}"),
+        params_from_iter(values.iter()),
+        |r| r.get(0),
+    )?;
+
+    // Exit time then id keep pages stable when the sort key ties.
+    let dir = if descending { "DESC" } else { "ASC" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {TRADE_COLUMNS}
+         FROM trades
+         WHERE {where_sql}
+         ORDER BY {} {dir}, exit_time_utc {dir}, id {dir}
+         LIMIT {limit} OFFSET {offset}",
+        sort.column()
+    ))?;
+    let rows = stmt.query_map(params_from_iter(values.iter()), trade_from_row)?;
+
+    let mut trades = Vec::new();
+    for r in rows {
+        trades.push(r?);
+    }
+    Ok(TradeQueryResult { trades, total })
+}
+
 pub fn get_trade_with_rules(conn: &Connection, trade_id: &str) -> anyhow::Result<TradeWithRules> {
     let trade: Trade = conn
         .query_row(
-            "SELECT id, market, symbol, side, qty, entry_time_utc, exit_time_utc, timezone, session, pnl_amount,
-                    pnl_includes_fees, fees, pnl_net, pnl_gross, notes, created_at_utc, updated_at_utc
-             FROM trades WHERE id = ?1",
+            &format!("SELECT {TRADE_COLUMNS} FROM trades WHERE id = ?1"),
             params![trade_id],
-            |row| {
-                Ok(Trade {
-                    id: row.get(0)?,
-                    market: row.get(1)?,
-                    symbol: row.get(2)?,
-                    side: row.get(3)?,
-                    qty: row.get(4)?,
-                    entry_time_utc: row.get(5)?,
-                    exit_time_utc: row.get(6)?,
-                    timezone: row.get(7)?,
-                    session: row.get(8)?,
-                    pnl_amount: row.get(9)?,
-                    pnl_includes_fees: row.get(10)?,
-                    fees: row.get(11)?,
-                    pnl_net: row.get(12)?,
-                    pnl_gross: row.get(13)?,
-                    notes: row.get(14)?,
-                    created_at_utc: row.get(15)?,
-                    updated_at_utc: row.get(16)?,
-                })
-            },
+            trade_from_row,
         )
         .context("trade not found")?;
 
@@ -100,26 +153,38 @@ pub fn get_trade_with_rules(conn: &Connection, trade_id: &str) -> anyhow::Result
         checked.insert(id, v);
     }
 
+    let executions = crate::executions::executions_for_trade(conn, trade_id)?;
+    let tags = crate::tags::tags_for_trade(conn, trade_id)?;
+
     Ok(TradeWithRules {
         trade,
         rules,
         checked,
+        executions,
+        tags,
     })
 }
 
 pub fn create_trade(conn: &Connection, input: TradeInput) -> anyhow::Result<Trade> {
     validate_trade(&input)?;
+    if let Some(account_id) = input.account_id.as_deref() {
+        crate::accounts::get_account(conn, account_id)?;
+    }
+    for tag_id in input.tags.iter().flatten() {
+        crate::tags::get_tag(conn, tag_id)?;
+    }
+    let instrument = instrument_for_trade(conn, input.instrument.as_deref(), &input.symbol)?;
+    let pnl = resolve_pnl(&input, instrument.as_ref())?;
 
     let id = Uuid::new_v4().to_string();
     let now = chrono::Utc::now().timestamp_millis();
 
-    let (pnl_net, pnl_gross) = derive_pnl(input.pnl_amount, input.fees, input.pnl_includes_fees);
-
     conn.execute(
         "INSERT INTO trades (
             id, market, symbol, side, qty, entry_time_utc, exit_time_utc, timezone, session,
-            pnl_amount, pnl_includes_fees, fees, pnl_net, pnl_gross, notes, created_at_utc, updated_at_utc
-        ) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17)",
+            pnl_amount, pnl_includes_fees, fees, pnl_net, pnl_gross, notes, instrument,
+            entry_price, exit_price, pnl_computed, pnl_mismatch, account_id, created_at_utc, updated_at_utc
+        ) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17,?18,?19,?20,?21,?22,?23)",
         params![
             id,
             input.market,
@@ -130,12 +195,18 @@ pub fn create_trade(conn: &Connection, input: TradeInput) -> anyhow::Result<Trad
             input.exit_time_utc,
             input.timezone,
             input.session,
-            input.pnl_amount,
+            pnl.amount,
             if input.pnl_includes_fees { 1 } else { 0 },
             input.fees,
-            pnl_net,
-            pnl_gross,
+            pnl.net,
+            pnl.gross,
             input.notes,
+            instrument.map(|i| i.root_symbol),
+            input.entry_price,
+            input.exit_price,
+            pnl.computed,
+            if pnl.mismatch { 1 } else { 0 },
+            input.account_id,
             now,
             now
         ],
@@ -158,19 +229,31 @@ pub fn create_trade(conn: &Connection, input: TradeInput) -> anyhow::Result<Trad
         )?;
     }
 
+    if let Some(tags) = &input.tags {
+        crate::tags::set_trade_tags(conn, &id, tags)?;
+    }
+
     Ok(get_trade_with_rules(conn, &id)?.trade)
 }
 
 pub fn update_trade(conn: &Connection, id: &str, input: TradeInput) -> anyhow::Result<Trade> {
     validate_trade(&input)?;
+    if let Some(account_id) = input.account_id.as_deref() {
+        crate::accounts::get_account(conn, account_id)?;
+    }
+    for tag_id in input.tags.iter().flatten() {
+        crate::tags::get_tag(conn, tag_id)?;
+    }
+    let instrument = instrument_for_trade(conn, input.instrument.as_deref(), &input.symbol)?;
+    let pnl = resolve_pnl(&input, instrument.as_ref())?;
 
     let now = chrono::Utc::now().timestamp_millis();
-    let (pnl_net, pnl_gross) = derive_pnl(input.pnl_amount, input.fees, input.pnl_includes_fees);
 
     conn.execute(
         "UPDATE trades SET
             market=?2, symbol=?3, side=?4, qty=?5, entry_time_utc=?6, exit_time_utc=?7, timezone=?8, session=?9,
-            pnl_amount=?10, pnl_includes_fees=?11, fees=?12, pnl_net=?13, pnl_gross=?14, notes=?15, updated_at_utc=?16
+            pnl_amount=?10, pnl_includes_fees=?11, fees=?12, pnl_net=?13, pnl_gross=?14, notes=?15, instrument=?16,
+            entry_price=?17, exit_price=?18, pnl_computed=?19, pnl_mismatch=?20, account_id=?21, updated_at_utc=?22
          WHERE id=?1",
         params![
             id,
@@ -182,12 +265,18 @@ pub fn update_trade(conn: &Connection, id: &str, input: TradeInput) -> anyhow::R
             input.exit_time_utc,
             input.timezone,
             input.session,
-            input.pnl_amount,
+            pnl.amount,
             if input.pnl_includes_fees { 1 } else { 0 },
             input.fees,
-            pnl_net,
-            pnl_gross,
+            pnl.net,
+            pnl.gross,
             input.notes,
+            instrument.map(|i| i.root_symbol),
+            input.entry_price,
+            input.exit_price,
+            pnl.computed,
+            if pnl.mismatch { 1 } else { 0 },
+            input.account_id,
             now
         ],
     )?;
@@ -202,6 +291,10 @@ pub fn update_trade(conn: &Connection, id: &str, input: TradeInput) -> anyhow::R
         }
     }
 
+    if let Some(tags) = &input.tags {
+        crate::tags::set_trade_tags(conn, id, tags)?;
+    }
+
     Ok(get_trade_with_rules(conn, id)?.trade)
 }
 
@@ -210,6 +303,66 @@ pub fn delete_trade(conn: &Connection, id: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+// Differences below half a cent are float noise, not typos.
+const PNL_MISMATCH_TOLERANCE: f64 = 0.005;
+
+struct ResolvedPnl {
+    amount: f64,
+    net: f64,
+    gross: f64,
+    computed: Option<f64>,
+    mismatch: bool,
+}
+
+/// Gross PnL implied by the prices: direction x qty x price delta x point value.
+pub fn compute_gross_pnl(side: &str, qty: f64, entry_price: f64, exit_price: f64, point_value: f64) -> anyhow::Result<f64> {
+    let direction = match side.trim().to_ascii_lowercase().as_str() {
+        "long" => 1.0,
+        "short" => -1.0,
+        other => anyhow::bail!("unsupported side: {other}"),
+    };
+    let gross = direction * qty * (exit_price - entry_price) * point_value;
+    Ok((gross * 100.0).round() / 100.0)
+}
+
+fn resolve_pnl(input: &TradeInput, instrument: Option<&Instrument>) -> anyhow::Result<ResolvedPnl> {
+    let computed = match (input.entry_price, input.exit_price, instrument) {
+        (Some(entry), Some(exit), Some(inst)) => Some(compute_gross_pnl(
+            &input.side,
+            input.qty,
+            entry,
+            exit,
+            inst.point_value,
+        )?),
+        _ => None,
+    };
+
+    match (input.pnl_amount, computed) {
+        (Some(amount), _) => {
+            let (net, gross) = derive_pnl(amount, input.fees, input.pnl_includes_fees);
+            let mismatch = computed.is_some_and(|c| (gross - c).abs() > PNL_MISMATCH_TOLERANCE);
+            Ok(ResolvedPnl {
+                amount,
+                net,
+                gross,
+                computed,
+                mismatch,
+            })
+        }
+        (None, Some(gross)) => {
+            let net = gross - input.fees;
+            Ok(ResolvedPnl {
+                amount: if input.pnl_includes_fees { net } else { gross },
+                net,
+                gross,
+                computed,
+                mismatch: false,
+            })
+        }
+        (None, None) => anyhow::bail!("pnl_amount is required unless entry/exit prices and a known instrument are given"),
+    }
+}
+
 fn derive_pnl(pnl_amount: f64, fees: f64, includes_fees: bool) -> (f64, f64) {
     if includes_fees {
         let net = pnl_amount;
@@ -229,11 +382,22 @@ fn validate_trade(input: &TradeInput) -> anyhow::Result<()> {
     if input.qty <= 0.0 {
         anyhow::bail!("qty must be > 0");
     }
-    if input.exit_time_utc <= input.entry_time_utc {
-        anyhow::bail!("exit time must be after entry time");
+    // Equal is allowed: fills built into a trade can share a timestamp.
+    if input.exit_time_utc < input.entry_time_utc {
+        anyhow::bail!("exit time must not be before entry time");
     }
     if input.fees < 0.0 {
         anyhow::bail!("fees must be >= 0");
     }
+    if input.entry_price.is_some() != input.exit_price.is_some() {
+        anyhow::bail!("entry and exit price must be given together");
+    }
+    if [input.entry_price, input.exit_price, input.pnl_amount]
+        .iter()
+        .flatten()
+        .any(|v| !v.is_finite())
+    {
+        anyhow::bail!("prices and pnl must be finite numbers");
+    }
     Ok(())
 }
@@ -1,5 +1,6 @@
 import { useEffect, useMemo, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import {
   Alert,
   AppBar,
@@ -23,8 +24,8 @@ import {
 import { open, save } from '@tauri-apps/plugin-dialog';
 import DeleteIcon from '@mui/icons-material/Delete';
 
-import type { AppStatus, Rule, Settings } from './ui/types';
-import { appGetStatus as getStatus, backupExport, backupImport, csvImportGeneric, rulesDelete, rulesList, rulesUpsert, settingsGet, settingsUpdate } from './ui/api';
+import type { AppStatus, DbInitResult, DbStatus, Rule, Settings } from './ui/types';
+import { appGetStatus as getStatus, DB_LOCKED_EVENT, dbLock, backupExport, backupImport, csvImportGeneric, rulesDelete, rulesList, rulesUpsert, settingsGet, settingsUpdate } from './ui/api';
 import TradesView from './ui/TradesView';
 import JournalView from './ui/JournalView';
 import ChangelogView from './ui/ChangelogView';
@@ -329,8 +330,15 @@ function ReadySection({
               if (!path) return;
 
               const res = await csvImportGeneric(path);
+              if (res.already_imported) {
+                setImportResult('This file was already imported. Undo that import first to import it again.');
+                return;
+              }
               const msg = `Created: ${res.created} | Skipped: ${res.skipped} | Errors: ${res.errors.length}`;
-              setImportResult(msg + (res.errors.length ? `\n\n` + res.errors.slice(0, 10).join('\n') : ''));
+              const placeholders = res.placeholder_instruments.length
+                ? `\n\nAdded with a placeholder tick size, check in the instrument catalog: ${res.placeholder_instruments.join(', ')}`
+                : '';
+              setImportResult(msg + placeholders + (res.errors.length ? `\n\n` + res.errors.slice(0, 10).join('\n') : ''));
             } catch (e) {
               setError(String(e));
             } finally {
@@ -379,6 +387,9 @@ export default function App() {
   const [initEncrypted, setInitEncrypted] = useState(true);
   const [initPass, setInitPass] = useState('');
   const [initPass2, setInitPass2] = useState('');
+  const [initRecovery, setInitRecovery] = useState(true);
+  // Shown once after setup; never stored.
+  const [recoveryKey, setRecoveryKey] = useState<string | null>(null);
 
   // unlock form
   const [unlockPass, setUnlockPass] = useState('');
@@ -393,6 +404,17 @@ export default function App() {
     })();
   }, []);
 
+  // The backend locks an idle encrypted database on its own; drop back to the unlock screen.
+  useEffect(() => {
+    const unlisten = listen<DbStatus>(DB_LOCKED_EVENT, (e) => {
+      setStatus({ db: e.payload });
+      setUnlockPass('');
+    });
+    return () => {
+      void unlisten.then((f) => f());
+    };
+  }, []);
+
   const ready = status && status.db.configured && (!status.db.encrypted || status.db.unlocked);
 
   useEffect(() => {
@@ -459,13 +481,15 @@ export default function App() {
     setBusy(true);
     setError('');
     try {
-      const res = await invoke<AppStatus>('db_init', {
+      const res = await invoke<DbInitResult>('db_init', {
         req: {
           encrypted: initEncrypted,
           passphrase: initEncrypted ? initPass : null,
+          recovery_key: initEncrypted && initRecovery,
         },
       });
-      setStatus(res);
+      setStatus({ db: res.db });
+      setRecoveryKey(res.recovery_key);
     } catch (e) {
       setError(String(e));
     } finally {
@@ -510,6 +534,17 @@ export default function App() {
               <StatusPill label="Configured" ok={status.db.configured} />
               <StatusPill label="Encrypted" ok={status.db.encrypted} />
               <StatusPill label="Unlocked" ok={status.db.unlocked} />
+              {status.db.encrypted && status.db.unlocked ? (
+                <Button
+                  variant="outlined"
+                  size="small"
+                  onClick={() => {
+                    void dbLock().then(setStatus, (e) => setError(String(e)));
+                  }}
+                >
+                  Lock
+                </Button>
+              ) : null}
             </Stack>
           ) : null}
           <Button variant="outlined" size="small" disabled>
@@ -555,6 +590,16 @@ export default function App() {
 
           {error ? <Alert severity="error">{error}</Alert> : null}
 
+          {recoveryKey ? (
+            <Alert severity="warning" onClose={() => setRecoveryKey(null)}>
+              <Typography variant="body2" sx={{ mb: 1 }}>
+                Your recovery key. Write it down and keep it somewhere safe. It is shown only once, and anyone who has it can
+                unlock your journal.
+              </Typography>
+              <Typography sx={{ fontFamily: 'monospace', fontWeight: 700 }}>{recoveryKey}</Typography>
+            </Alert>
+          ) : null}
+
           {/* DB status pills moved to the top-right app bar (next to Offline) */}
 
           {!ready ? (
@@ -580,7 +625,11 @@ export default function App() {
                         type="password"
                         value={initPass}
                         onChange={(e) => setInitPass(e.target.value)}
-                        helperText="Minimum 8 characters. If you forget it, your data cannot be recovered."
+                        helperText={
+                          initRecovery
+                            ? 'Minimum 8 characters.'
+                            : 'Minimum 8 characters. If you forget it, your data cannot be recovered.'
+                        }
                         fullWidth
                       />
                       <TextField
@@ -590,6 +639,10 @@ export default function App() {
                         onChange={(e) => setInitPass2(e.target.value)}
                         fullWidth
                       />
+                      <FormControlLabel
+                        control={<Switch checked={initRecovery} onChange={(_, v) => setInitRecovery(v)} />}
+                        label="Create a recovery key (unlocks the database if you forget the passphrase)"
+                      />
                     </Stack>
                   ) : null}
 
@@ -611,7 +664,7 @@ export default function App() {
                   </Typography>
                   <Stack spacing={2} sx={{ mt: 2 }}>
                     <TextField
-                      label="Passphrase"
+                      label="Passphrase or recovery key"
                       type="password"
                       value={unlockPass}
                       onChange={(e) => setUnlockPass(e.target.value)}
@@ -1,4 +1,4 @@
-import { useEffect, useMemo, useState } from 'react';
+import { useEffect, useMemo, useRef, useState } from 'react';
 import {
   Alert,
   Box,
@@ -17,8 +17,8 @@ import {
   TextField,
   Typography,
 } from '@mui/material';
-import type { Rule, Settings, Trade, TradeInput, TradeWithRules } from './types';
-import { rulesList, settingsGet, tradesCreate, tradesDelete, tradesGet, tradesList, tradesUpdate } from './api';
+import type { Account, Rule, Settings, Trade, TradeFilter, TradeInput, TradeStats, TradeWithRules } from './types';
+import { accountsList, analyticsStats, rulesList, settingsGet, tradesCreate, tradesDelete, tradesGet, tradesQuery, tradesUpdate } from './api';
 import { parseLocalDateTimeInput, toLocalDateTimeInputValue } from './time';
 
 const SESSIONS = [
@@ -59,10 +59,13 @@ type TradeFormState = {
   entryLocal: string;
   exitLocal: string;
   session: string;
-  pnlAmount: number;
+  entryPrice: string;
+  exitPrice: string;
+  pnlAmount: string; // '' = compute from prices
   fees: number;
   pnlIncludesFees: boolean;
   notes: string;
+  accountId: string | null;
   rulesChecked: Record<string, boolean>;
 };
 
@@ -80,10 +83,13 @@ function buildDefaultForm(rules: Rule[]): TradeFormState {
     entryLocal: toLocalDateTimeInputValue(now),
     exitLocal: toLocalDateTimeInputValue(later),
     session: 'ny',
-    pnlAmount: 0,
+    entryPrice: '',
+    exitPrice: '',
+    pnlAmount: '',
     fees: 0,
     pnlIncludesFees: true,
     notes: '',
+    accountId: null,
     rulesChecked,
   };
 }
@@ -99,7 +105,11 @@ export default function TradesView({
 }) {
   const [settings, setSettings] = useState<Settings | null>(null);
   const [rules, setRules] = useState<Rule[]>([]);
+  const [accounts, setAccounts] = useState<Account[]>([]);
   const [trades, setTrades] = useState<Trade[]>([]);
+  const [matchCount, setMatchCount] = useState(0);
+  // Stats come from the backend so they cover every matching trade, not just the loaded page.
+  const [stats, setStats] = useState<TradeStats | null>(null);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState('');
 
@@ -110,7 +120,9 @@ export default function TradesView({
   const [confirmDeleteId, setConfirmDeleteId] = useState<string | null>(null);
 
   // Filters
+  const [filterAccount, setFilterAccount] = useState<string>('all');
   const [filterSymbol, setFilterSymbol] = useState('');
+  const [symbolQuery, setSymbolQuery] = useState(''); // filterSymbol once typing pauses
   const [filterSession, setFilterSession] = useState<string>('all');
   const [filterOutcome, setFilterOutcome] = useState<'all' | 'win' | 'loss'>('all');
   const [filterStart, setFilterStart] = useState(''); // YYYY-MM-DD
@@ -118,24 +130,63 @@ export default function TradesView({
 
   const tz = timezone ?? settings?.timezone ?? undefined;
 
+  const filter = useMemo<TradeFilter>(
+    () => ({
+      account_id: filterAccount === 'all' ? null : filterAccount,
+      symbol: symbolQuery.trim() || null,
+      session: filterSession === 'all' ? null : filterSession,
+      outcome: filterOutcome === 'all' ? null : filterOutcome,
+      start_date: filterStart || null,
+      end_date: filterEnd || null,
+    }),
+    [filterAccount, symbolQuery, filterSession, filterOutcome, filterStart, filterEnd],
+  );
+
+  // Only the latest request may update the list; older ones finishing late are dropped.
+  const requestSeq = useRef(0);
+
   async function refresh() {
+    const seq = ++requestSeq.current;
     setLoading(true);
     setError('');
     try {
-      const [s, r, t] = await Promise.all([settingsGet(), rulesList(), tradesList()]);
-      setSettings(s);
-      setRules(r);
-      setTrades(t);
+      const [q, st] = await Promise.all([tradesQuery(filter), analyticsStats(filter)]);
+      if (seq !== requestSeq.current) return;
+      setTrades(q.trades);
+      setMatchCount(q.total);
+      setStats(st);
     } catch (e) {
-      setError(String(e));
+      if (seq === requestSeq.current) setError(String(e));
     } finally {
-      setLoading(false);
+      if (seq === requestSeq.current) setLoading(false);
     }
   }
 
+  useEffect(() => {
+    const t = setTimeout(() => setSymbolQuery(filterSymbol), 300);
+    return () => clearTimeout(t);
+  }, [filterSymbol]);
+
   useEffect(() => {
     void refresh();
     // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [filter]);
+
+  useEffect(() => {
+    let cancelled = false;
+    Promise.all([settingsGet(), rulesList(), accountsList()])
+      .then(([s, r, a]) => {
+        if (cancelled) return;
+        setSettings(s);
+        setRules(r);
+        setAccounts(a);
+      })
+      .catch((e) => {
+        if (!cancelled) setError(String(e));
+      });
+    return () => {
+      cancelled = true;
+    };
   }, []);
 
   useEffect(() => {
@@ -147,29 +198,7 @@ export default function TradesView({
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [openTradeId]);
 
-  const filteredTrades = useMemo(() => {
-    const sym = filterSymbol.trim().toLowerCase();
-
-    const startMs = filterStart ? new Date(`${filterStart}T00:00:00`).getTime() : null;
-    const endMs = filterEnd ? new Date(`${filterEnd}T23:59:59`).getTime() : null;
-
-    return trades.filter((t) => {
-      if (sym && !t.symbol.toLowerCase().includes(sym)) return false;
-      if (filterSession !== 'all' && t.session !== filterSession) return false;
-      if (filterOutcome === 'win' && !(t.pnl_net > 0)) return false;
-      if (filterOutcome === 'loss' && !(t.pnl_net < 0)) return false;
-      if (startMs != null && t.exit_time_utc < startMs) return false;
-      if (endMs != null && t.exit_time_utc > endMs) return false;
-      return true;
-    });
-  }, [trades, filterSymbol, filterSession, filterOutcome, filterStart, filterEnd]);
-
-  const stats = useMemo(() => {
-    const total = filteredTrades.reduce((acc, t) => acc + (t.pnl_net ?? 0), 0);
-    const wins = filteredTrades.filter((t) => (t.pnl_net ?? 0) > 0).length;
-    const losses = filteredTrades.filter((t) => (t.pnl_net ?? 0) < 0).length;
-    return { total, wins, losses, count: filteredTrades.length };
-  }, [filteredTrades]);
+  const total = stats?.net_pnl ?? 0;
 
   function newTrade() {
     if (!settings) return;
@@ -199,10 +228,14 @@ export default function TradesView({
         entryLocal: toLocalDateTimeInputValue(entry),
         exitLocal: toLocalDateTimeInputValue(exit),
         session: t.session,
-        pnlAmount: t.pnl_amount,
+        entryPrice: t.entry_price == null ? '' : String(t.entry_price),
+        exitPrice: t.exit_price == null ? '' : String(t.exit_price),
+        // Trades whose PnL came from prices stay in "auto" mode when edited.
+        pnlAmount: t.pnl_computed != null && t.pnl_mismatch === 0 ? '' : String(t.pnl_amount),
         fees: t.fees,
         pnlIncludesFees: t.pnl_includes_fees === 1,
         notes: t.notes,
+        accountId: t.account_id,
         rulesChecked,
       });
       setOpen(true);
@@ -231,10 +264,13 @@ export default function TradesView({
         exit_time_utc: exit.getTime(),
         timezone: settings.timezone,
         session: form.session,
-        pnl_amount: Number(form.pnlAmount),
+        entry_price: form.entryPrice.trim() === '' ? null : Number(form.entryPrice),
+        exit_price: form.exitPrice.trim() === '' ? null : Number(form.exitPrice),
+        pnl_amount: form.pnlAmount.trim() === '' ? null : Number(form.pnlAmount),
         pnl_includes_fees: form.pnlIncludesFees,
         fees: Number(form.fees),
         notes: form.notes ?? '',
+        account_id: form.accountId,
         rules_checked: form.rulesChecked,
       };
 
@@ -274,13 +310,22 @@ export default function TradesView({
       <Stack direction={{ xs: 'column', sm: 'row' }} spacing={2} sx={{ alignItems: { sm: 'center' }, mb: 2 }}>
         <Stack direction={{ xs: 'column', sm: 'row' }} spacing={1} sx={{ flex: 1 }}>
           <Button variant="outlined" size="small" disabled sx={{ opacity: 1, justifyContent: 'flex-start' }}>
-            Count: {stats.count}
+            Count: {stats?.trade_count ?? 0}
+          </Button>
+          <Button variant="outlined" size="small" disabled sx={{ opacity: 1, justifyContent: 'flex-start' }}>
+            Wins: {stats?.wins ?? 0}
           </Button>
           <Button variant="outlined" size="small" disabled sx={{ opacity: 1, justifyContent: 'flex-start' }}>
-            Wins: {stats.wins}
+            Losses: {stats?.losses ?? 0}
           </Button>
           <Button variant="outlined" size="small" disabled sx={{ opacity: 1, justifyContent: 'flex-start' }}>
-            Losses: {stats.losses}
+            Win rate: {(stats?.win_rate_pct ?? 0).toFixed(1)}%
+          </Button>
+          <Button variant="outlined" size="small" disabled sx={{ opacity: 1, justifyContent: 'flex-start' }}>
+            PF: {stats?.profit_factor == null ? '—' : stats.profit_factor.toFixed(2)}
+          </Button>
+          <Button variant="outlined" size="small" disabled sx={{ opacity: 1, justifyContent: 'flex-start' }}>
+            Expectancy: {money(stats?.expectancy ?? 0)}
           </Button>
           <Button
             variant="outlined"
@@ -289,11 +334,11 @@ export default function TradesView({
             sx={{
               opacity: 1,
               justifyContent: 'flex-start',
-              borderColor: stats.total >= 0 ? '#22c55e' : '#ef4444',
-              color: stats.total >= 0 ? '#22c55e' : '#ef4444',
+              borderColor: total >= 0 ? '#22c55e' : '#ef4444',
+              color: total >= 0 ? '#22c55e' : '#ef4444',
             }}
           >
-            Total: {money(stats.total)}
+            Total: {money(total)}
           </Button>
         </Stack>
 
@@ -311,6 +356,21 @@ export default function TradesView({
       {loading ? <Alert severity="info">Loading…</Alert> : null}
 
       <Stack direction={{ xs: 'column', sm: 'row' }} spacing={1} sx={{ mb: 2 }}>
+        <FormControl size="small" sx={{ minWidth: 160 }}>
+          <InputLabel>Account</InputLabel>
+          <Select
+            label="Account"
+            value={filterAccount}
+            onChange={(e) => setFilterAccount(e.target.value)}
+          >
+            <MenuItem value="all">All</MenuItem>
+            {accounts.map((a) => (
+              <MenuItem key={a.id} value={a.id}>
+                {a.name}
+              </MenuItem>
+            ))}
+          </Select>
+        </FormControl>
         <TextField
           label="Symbol"
           value={filterSymbol}
@@ -366,7 +426,9 @@ export default function TradesView({
           variant="outlined"
           size="small"
           onClick={() => {
+            setFilterAccount('all');
             setFilterSymbol('');
+            setSymbolQuery('');
             setFilterSession('all');
             setFilterOutcome('all');
             setFilterStart('');
@@ -377,10 +439,15 @@ export default function TradesView({
         </Button>
       </Stack>
 
-      {!loading && filteredTrades.length === 0 ? <Alert severity="info">No trades match your filters.</Alert> : null}
+      {!loading && trades.length === 0 ? <Alert severity="info">No trades match your filters.</Alert> : null}
+      {matchCount > trades.length ? (
+        <Typography variant="body2" color="text.secondary" sx={{ mb: 1 }}>
+          Showing the latest {trades.length} of {matchCount} matching trades.
+        </Typography>
+      ) : null}
 
       <Stack spacing={1}>
-        {filteredTrades.map((t) => (
+        {trades.map((t) => (
           <Box
             key={t.id}
             sx={{
@@ -399,6 +466,11 @@ export default function TradesView({
                 <Typography variant="body2" color="text.secondary">
                   Entry: {fmtDate(t.entry_time_utc, tz)} · Exit: {fmtDate(t.exit_time_utc, tz)} · Session: {t.session}
                 </Typography>
+                {t.pnl_mismatch === 1 && t.pnl_computed != null ? (
+                  <Typography variant="body2" color="warning.main">
+                    PnL differs from prices (computed gross {money(t.pnl_computed)})
+                  </Typography>
+                ) : null}
               </Box>
               <Stack direction="row" spacing={1} sx={{ alignItems: 'center' }}>
                 {(() => {
@@ -470,6 +542,21 @@ export default function TradesView({
                   onChange={(e) => setForm({ ...form, qty: Number(e.target.value) })}
                   fullWidth
                 />
+                <FormControl fullWidth>
+                  <InputLabel>Account</InputLabel>
+                  <Select
+                    label="Account"
+                    value={form.accountId ?? ''}
+                    onChange={(e) => setForm({ ...form, accountId: e.target.value || null })}
+                  >
+                    <MenuItem value="">None</MenuItem>
+                    {accounts.map((a) => (
+                      <MenuItem key={a.id} value={a.id}>
+                        {a.name}
+                      </MenuItem>
+                    ))}
+                  </Select>
+                </FormControl>
               </Stack>
 
               <Stack direction={{ xs: 'column', sm: 'row' }} spacing={2}>
@@ -505,12 +592,31 @@ export default function TradesView({
                 </FormControl>
               </Stack>
 
+              <Stack direction={{ xs: 'column', sm: 'row' }} spacing={2}>
+                <TextField
+                  label="Entry price"
+                  type="number"
+                  value={form.entryPrice}
+                  onChange={(e) => setForm({ ...form, entryPrice: e.target.value })}
+                  fullWidth
+                />
+                <TextField
+                  label="Exit price"
+                  type="number"
+                  value={form.exitPrice}
+                  onChange={(e) => setForm({ ...form, exitPrice: e.target.value })}
+                  fullWidth
+                />
+              </Stack>
+
               <Stack direction={{ xs: 'column', sm: 'row' }} spacing={2}>
                 <TextField
                   label="PnL"
                   type="number"
                   value={form.pnlAmount}
-                  onChange={(e) => setForm({ ...form, pnlAmount: Number(e.target.value) })}
+                  onChange={(e) => setForm({ ...form, pnlAmount: e.target.value })}
+                  placeholder="Auto from prices"
+                  InputLabelProps={{ shrink: true }}
                   fullWidth
                 />
                 <TextField
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { AppStatus, Settings, Rule, Trade, TradeInput, TradeWithRules, DaySummary, TradeHighlight, JournalEntry, CsvImportResult } from './types';
+import type { AppStatus, CipherParams, Settings, Rule, Instrument, Account, AccountInput, PropRules, PropRulesInput, PropReport, Trade, TradeFilter, TradeSort, TradeQueryResult, TradeStats, EquityCurve, BreakdownBy, BreakdownBucket, AdherenceInterval, RuleAdherence, SearchHit, Tag, TagGroup, TagInput, TagPerformance, Attachment, AttachmentInput, AttachmentData, TradeInput, TradeWithRules, Execution, ExecutionInput, MatchMethod, BuildTradesResult, DaySummary, TradeHighlight, JournalEntry, CsvImportResult, ImportMode, CsvPreviewRow, ImportBatch, ImportProfile, ImportProfileInput } from './types';
 
 export async function appGetStatus(): Promise<AppStatus> {
   return invoke<AppStatus>('app_get_status');
@@ -25,8 +25,139 @@ export async function rulesDelete(id: string): Promise<void> {
   return invoke<void>('rules_delete', { id });
 }
 
-export async function tradesList(limit = 200, offset = 0): Promise<Trade[]> {
-  return invoke<Trade[]>('trades_list', { req: { limit, offset } });
+export async function instrumentsList(): Promise<Instrument[]> {
+  return invoke<Instrument[]>('instruments_list');
+}
+
+export async function instrumentsUpsert(instrument: Instrument): Promise<void> {
+  return invoke<void>('instruments_upsert', { req: instrument });
+}
+
+export async function instrumentsDelete(root_symbol: string): Promise<void> {
+  return invoke<void>('instruments_delete', { rootSymbol: root_symbol });
+}
+
+export async function accountsList(): Promise<Account[]> {
+  return invoke<Account[]>('accounts_list');
+}
+
+export async function accountsCreate(input: AccountInput): Promise<Account> {
+  return invoke<Account>('accounts_create', { input });
+}
+
+export async function accountsUpdate(id: string, input: AccountInput): Promise<Account> {
+  return invoke<Account>('accounts_update', { req: { id, input } });
+}
+
+export async function accountsDelete(id: string): Promise<void> {
+  return invoke<void>('accounts_delete', { id });
+}
+
+export async function propRulesGet(account_id: string): Promise<PropRules | null> {
+  return invoke<PropRules | null>('prop_rules_get', { accountId: account_id });
+}
+
+export async function propRulesSet(account_id: string, input: PropRulesInput): Promise<PropRules> {
+  return invoke<PropRules>('prop_rules_set', { req: { account_id, input } });
+}
+
+export async function propRulesDelete(account_id: string): Promise<void> {
+  return invoke<void>('prop_rules_delete', { accountId: account_id });
+}
+
+export async function propReport(account_id: string): Promise<PropReport> {
+  return invoke<PropReport>('prop_report', { accountId: account_id });
+}
+
+export async function analyticsStats(filter: TradeFilter = {}): Promise<TradeStats> {
+  return invoke<TradeStats>('analytics_stats', { filter });
+}
+
+export async function analyticsEquityCurve(filter: TradeFilter = {}): Promise<EquityCurve> {
+  return invoke<EquityCurve>('analytics_equity_curve', { filter });
+}
+
+export async function analyticsBreakdown(
+  by: BreakdownBy,
+  filter: TradeFilter = {},
+  bucket_minutes: number | null = null, // time_of_day only; defaults to 60
+): Promise<BreakdownBucket[]> {
+  return invoke<BreakdownBucket[]>('analytics_breakdown', { req: { filter, by, bucket_minutes } });
+}
+
+export async function analyticsRuleAdherence(
+  filter: TradeFilter = {},
+  interval: AdherenceInterval = 'week',
+): Promise<RuleAdherence[]> {
+  return invoke<RuleAdherence[]>('analytics_rule_adherence', { req: { filter, interval } });
+}
+
+export async function tagGroupsList(): Promise<TagGroup[]> {
+  return invoke<TagGroup[]>('tag_groups_list');
+}
+
+export async function tagGroupsUpsert(group: TagGroup): Promise<void> {
+  return invoke<void>('tag_groups_upsert', { req: group });
+}
+
+export async function tagGroupsDelete(id: string): Promise<void> {
+  return invoke<void>('tag_groups_delete', { id });
+}
+
+export async function tagsList(): Promise<Tag[]> {
+  return invoke<Tag[]>('tags_list');
+}
+
+export async function tagsCreate(input: TagInput): Promise<Tag> {
+  return invoke<Tag>('tags_create', { input });
+}
+
+export async function tagsUpdate(id: string, input: TagInput): Promise<Tag> {
+  return invoke<Tag>('tags_update', { req: { id, input } });
+}
+
+export async function tagsDelete(id: string): Promise<void> {
+  return invoke<void>('tags_delete', { id });
+}
+
+export async function analyticsTagPerformance(filter: TradeFilter = {}): Promise<TagPerformance[]> {
+  return invoke<TagPerformance[]>('analytics_tag_performance', { filter });
+}
+
+export async function attachmentsAdd(input: AttachmentInput): Promise<Attachment> {
+  return invoke<Attachment>('attachments_add', { input });
+}
+
+export async function attachmentsList(
+  target: { trade_id: string; date_local?: null } | { trade_id?: null; date_local: string },
+): Promise<Attachment[]> {
+  return invoke<Attachment[]>('attachments_list', { req: target });
+}
+
+export async function attachmentsGet(id: string): Promise<AttachmentData> {
+  return invoke<AttachmentData>('attachments_get', { id });
+}
+
+export async function attachmentsDelete(id: string): Promise<void> {
+  return invoke<void>('attachments_delete', { id });
+}
+
+export async function search(query: string, limit = 50): Promise<SearchHit[]> {
+  return invoke<SearchHit[]>('search', { req: { query, limit } });
+}
+
+export async function tradesList(limit = 200, offset = 0, account_id: string | null = null): Promise<Trade[]> {
+  return invoke<Trade[]>('trades_list', { req: { limit, offset, account_id } });
+}
+
+export async function tradesQuery(
+  filter: TradeFilter = {},
+  sort: TradeSort = 'exit_time',
+  descending = true,
+  limit = 200,
+  offset = 0,
+): Promise<TradeQueryResult> {
+  return invoke<TradeQueryResult>('trades_query', { req: { filter, sort, descending, limit, offset } });
 }
 
 export async function tradesGet(id: string): Promise<TradeWithRules> {
@@ -45,12 +176,59 @@ export async function tradesDelete(id: string): Promise<void> {
   return invoke<void>('trades_delete', { id });
 }
 
-export async function journalMonthSummary(year: number, month: number): Promise<DaySummary[]> {
-  return invoke<DaySummary[]>('journal_month_summary', { req: { year, month } });
+export async function executionsList(limit = 200, offset = 0, unmatched_only = false): Promise<Execution[]> {
+  return invoke<Execution[]>('executions_list', { req: { limit, offset, unmatched_only } });
+}
+
+export async function executionsCreate(input: ExecutionInput): Promise<string> {
+  return invoke<string>('executions_create', { input });
+}
+
+export async function executionsDelete(id: string): Promise<void> {
+  return invoke<void>('executions_delete', { id });
+}
+
+export async function executionsBuildTrades(method: MatchMethod): Promise<BuildTradesResult> {
+  return invoke<BuildTradesResult>('executions_build_trades', { req: { method } });
+}
+
+export async function journalMonthSummary(
+  year: number,
+  month: number,
+  account_id: string | null = null,
+): Promise<DaySummary[]> {
+  return invoke<DaySummary[]>('journal_month_summary', { req: { year, month, account_id } });
+}
+
+export async function journalDayTrades(date_local: string, account_id: string | null = null): Promise<TradeHighlight[]> {
+  return invoke<TradeHighlight[]>('journal_day_trades', { req: { date_local, account_id } });
+}
+
+// The backend also emits DB_LOCKED_EVENT (with the new DbStatus) when it auto-locks.
+export const DB_LOCKED_EVENT = 'db-locked';
+
+export async function dbLock(): Promise<AppStatus> {
+  return invoke<AppStatus>('db_lock');
+}
+
+export async function dbSetAutoLock(minutes: number | null): Promise<AppStatus> {
+  return invoke<AppStatus>('db_set_auto_lock', { req: { minutes } });
+}
+
+export async function dbChangePassphrase(current_passphrase: string, new_passphrase: string): Promise<AppStatus> {
+  return invoke<AppStatus>('db_change_passphrase', { req: { current_passphrase, new_passphrase } });
+}
+
+export async function dbEncrypt(passphrase: string): Promise<AppStatus> {
+  return invoke<AppStatus>('db_encrypt', { req: { passphrase } });
+}
+
+export async function dbDecrypt(current_passphrase: string): Promise<AppStatus> {
+  return invoke<AppStatus>('db_decrypt', { req: { current_passphrase } });
 }
 
-export async function journalDayTrades(date_local: string): Promise<TradeHighlight[]> {
-  return invoke<TradeHighlight[]>('journal_day_trades', { req: { date_local } });
+export async function dbUpgradeCipher(passphrase: string, cipher?: CipherParams): Promise<AppStatus> {
+  return invoke<AppStatus>('db_upgrade_cipher', { req: { passphrase, cipher: cipher ?? null } });
 }
 
 export async function backupExport(dest_path: string): Promise<AppStatus> {
@@ -69,6 +247,50 @@ export async function journalEntryUpsert(date_local: string, text: string): Prom
   return invoke<void>('journal_entry_upsert', { req: { date_local, text } });
 }
 
-export async function csvImportGeneric(path: string): Promise<CsvImportResult> {
-  return invoke<CsvImportResult>('csv_import_generic', { req: { path } });
+export async function csvImportGeneric(path: string, profile_id: string | null = null, mode: ImportMode = 'lenient'): Promise<CsvImportResult> {
+  return invoke<CsvImportResult>('csv_import_generic', { req: { path, profile_id, mode } });
+}
+
+export async function csvImportPreview(path: string, profile_id: string | null = null, limit = 20): Promise<CsvPreviewRow[]> {
+  return invoke<CsvPreviewRow[]>('csv_import_preview', { req: { path, profile_id, limit } });
+}
+
+export async function importsList(limit = 100, offset = 0): Promise<ImportBatch[]> {
+  return invoke<ImportBatch[]>('imports_list', { req: { limit, offset } });
+}
+
+export async function importsUndo(id: string): Promise<void> {
+  return invoke<void>('imports_undo', { id });
+}
+
+export async function importProfilesList(): Promise<ImportProfile[]> {
+  return invoke<ImportProfile[]>('import_profiles_list');
+}
+
+export async function importProfilesCreate(input: ImportProfileInput): Promise<ImportProfile> {
+  return invoke<ImportProfile>('import_profiles_create', { input });
+}
+
+export async function importProfilesUpdate(id: string, input: ImportProfileInput): Promise<ImportProfile> {
+  return invoke<ImportProfile>('import_profiles_update', { req: { id, input } });
+}
+
+export async function importProfilesDelete(id: string): Promise<void> {
+  return invoke<void>('import_profiles_delete', { id });
+}
+
+export async function csvImportNinjaTrader(path: string, day_first = false, method: MatchMethod = 'fifo', mode: ImportMode = 'lenient'): Promise<CsvImportResult> {
+  return invoke<CsvImportResult>('csv_import_ninjatrader', { req: { path, day_first, method, mode } });
+}
+
+export async function csvImportTradovate(path: string, method: MatchMethod = 'fifo', mode: ImportMode = 'lenient'): Promise<CsvImportResult> {
+  return invoke<CsvImportResult>('csv_import_tradovate', { req: { path, method, mode } });
+}
+
+export async function csvImportRithmic(path: string, method: MatchMethod = 'fifo', mode: ImportMode = 'lenient'): Promise<CsvImportResult> {
+  return invoke<CsvImportResult>('csv_import_rithmic', { req: { path, method, mode } });
+}
+
+export async function ibkrImportFlex(path: string, method: MatchMethod = 'fifo', mode: ImportMode = 'lenient'): Promise<CsvImportResult> {
+  return invoke<CsvImportResult>('ibkr_import_flex', { req: { path, method, mode } });
 }
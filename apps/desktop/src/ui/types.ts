@@ -2,12 +2,27 @@ export type DbStatus = {
   configured: boolean;
   encrypted: boolean;
   unlocked: boolean;
+  // Encrypted with weaker settings than new databases get; offer dbUpgradeCipher.
+  cipher_outdated: boolean;
+  auto_lock_minutes: number | null;
+};
+
+export type CipherParams = {
+  page_size: number;
+  kdf_iter: number;
+  hmac_algorithm: 'HMAC_SHA1' | 'HMAC_SHA256' | 'HMAC_SHA512';
+  kdf_algorithm: 'PBKDF2_HMAC_SHA1' | 'PBKDF2_HMAC_SHA256' | 'PBKDF2_HMAC_SHA512';
 };
 
 export type AppStatus = {
   db: DbStatus;
 };
 
+export type DbInitResult = AppStatus & {
+  // Only when requested at setup; shown to the user once.
+  recovery_key: string | null;
+};
+
 export type Settings = {
   timezone: string;
 };
@@ -18,6 +33,100 @@ export type Rule = {
   sort_order: number;
 };
 
+export type Instrument = {
+  root_symbol: string;
+  name: string;
+  exchange: string;
+  currency: string;
+  tick_size: number;
+  tick_value: number;
+  point_value: number;
+};
+
+export type AccountType = 'eval' | 'funded' | 'personal' | 'sim';
+
+export type Account = {
+  id: string;
+  name: string;
+  broker: string;
+  account_type: AccountType;
+  starting_balance: number;
+  currency: string;
+  created_at_utc: number;
+  updated_at_utc: number;
+};
+
+export type AccountInput = {
+  name: string;
+  broker?: string;
+  account_type: AccountType;
+  starting_balance: number;
+  currency: string;
+};
+
+export type DrawdownMode = 'eod' | 'intraday' | 'static';
+
+export type PropRulesInput = {
+  profit_target: number | null;
+  max_drawdown: number | null;
+  drawdown_mode: DrawdownMode;
+  drawdown_lock_balance: number | null; // trailing threshold stops rising here
+  daily_loss_limit: number | null;
+  consistency_pct: number | null; // max share of total profit from the best day, 0-100
+  min_trading_days: number | null;
+};
+
+export type PropRules = PropRulesInput & {
+  account_id: string;
+  updated_at_utc: number;
+};
+
+export type PropViolation = {
+  rule: 'drawdown' | 'daily_loss' | 'consistency';
+  trade_id: string;
+  exit_time_utc: number;
+  session_date: string; // futures session (Chicago, rolls at 17:00)
+  message: string;
+};
+
+export type PropReport = {
+  account_id: string;
+  starting_balance: number;
+  balance: number;
+  high_water_mark: number;
+  drawdown_threshold: number | null;
+  drawdown_buffer: number | null;
+  daily_loss_remaining: number | null; // for the current session
+  days_traded: number;
+  min_trading_days: number | null;
+  profit: number;
+  profit_target: number | null;
+  target_progress_pct: number | null;
+  best_day_pnl: number;
+  best_day_share_pct: number | null;
+  violations: PropViolation[];
+  passed: boolean;
+};
+
+export type TagGroup = {
+  id: string;
+  name: string; // Setup, Mistake, Market Condition, ...
+  sort_order: number;
+};
+
+export type Tag = {
+  id: string;
+  group_id: string | null;
+  name: string;
+  color: string; // CSS color; empty = default
+};
+
+export type TagInput = {
+  group_id?: string | null;
+  name: string;
+  color?: string;
+};
+
 export type Trade = {
   id: string;
   market: string;
@@ -34,6 +143,12 @@ export type Trade = {
   pnl_net: number;
   pnl_gross: number;
   notes: string;
+  instrument: string | null;
+  entry_price: number | null;
+  exit_price: number | null;
+  pnl_computed: number | null; // gross, from prices x point value
+  pnl_mismatch: number; // 0/1 stored
+  account_id: string | null;
   created_at_utc: number;
   updated_at_utc: number;
 };
@@ -47,10 +162,15 @@ export type TradeInput = {
   exit_time_utc: number;
   timezone: string;
   session: string;
-  pnl_amount: number;
+  entry_price?: number | null;
+  exit_price?: number | null;
+  pnl_amount?: number | null; // manual override; computed from prices when omitted
   pnl_includes_fees: boolean;
   fees: number;
   notes: string;
+  instrument?: string | null; // root symbol; derived from symbol when omitted
+  account_id?: string | null;
+  tags?: string[]; // tag ids; omitted = keep the trade's tags
   rules_checked?: Record<string, boolean>;
 };
 
@@ -58,6 +178,49 @@ export type TradeWithRules = {
   trade: Trade;
   rules: Rule[];
   checked: Record<string, boolean>;
+  executions: LinkedExecution[]; // fill ladder, oldest first
+  tags: Tag[];
+};
+
+export type Execution = {
+  id: string;
+  source: string;
+  external_id: string;
+  account: string;
+  symbol: string;
+  instrument: string | null;
+  side: 'buy' | 'sell';
+  qty: number;
+  price: number;
+  fees: number;
+  realized_pnl: number | null; // broker-reported, net of commissions
+  time_utc: number;
+  created_at_utc: number;
+};
+
+export type ExecutionInput = {
+  source: string;
+  external_id?: string;
+  account?: string;
+  symbol: string;
+  side: string;
+  qty: number;
+  price: number;
+  fees: number;
+  realized_pnl?: number | null;
+  time_utc: number;
+};
+
+export type LinkedExecution = {
+  execution: Execution;
+  qty: number; // portion of the fill allocated to this trade
+};
+
+export type MatchMethod = 'fifo' | 'average_cost';
+
+export type BuildTradesResult = {
+  created: number;
+  errors: string[];
 };
 
 export type DaySummary = {
@@ -81,7 +244,215 @@ export type JournalEntry = {
 };
 
 export type CsvImportResult = {
+  import_id: string | null; // pass to importsUndo to roll the batch back; null when rolled back
   created: number;
   skipped: number;
   errors: string[];
+  rolled_back: boolean; // strict mode hit an error and imported nothing
+  placeholder_instruments: string[]; // roots added with a made-up tick size; fix them in the catalog
+  already_imported: string | null; // earlier import of the identical file; nothing was imported
+};
+
+// lenient: import the good lines and report the bad ones; strict: all or nothing.
+export type ImportMode = 'lenient' | 'strict';
+
+export type ImportBatch = {
+  id: string;
+  source: 'generic' | 'ninjatrader' | 'tradovate' | 'rithmic' | 'ibkr';
+  file_name: string;
+  file_hash: string; // sha256, hex
+  rows_total: number;
+  created: number;
+  skipped: number;
+  errors: number;
+  imported_at_utc: number;
+};
+
+export type CsvPreviewRow = {
+  line: number;
+  trade: TradeInput | null; // null with no error = skipped
+  error: string | null;
+};
+
+export type ImportField =
+  | 'symbol'
+  | 'side'
+  | 'qty'
+  | 'entry_time'
+  | 'exit_time'
+  | 'entry_price'
+  | 'exit_price'
+  | 'pnl'
+  | 'fees'
+  | 'notes'
+  | 'account'
+  | 'session'
+  | 'market'
+  | 'instrument';
+
+export type ImportProfileInput = {
+  name: string;
+  columns: Record<string, ImportField>; // source header -> field
+  datetime_format: string; // chrono format, e.g. "%d/%m/%Y %H:%M"; empty = ISO
+  timezone: string | null; // null = app timezone
+  decimal_separator: string;
+  thousands_separator: string;
+  side_map: Record<string, 'long' | 'short'>;
+  pnl_includes_fees: boolean;
+};
+
+export type ImportProfile = ImportProfileInput & {
+  id: string;
+  created_at_utc: number;
+  updated_at_utc: number;
+};
+
+export type TradeFilter = {
+  account_id?: string | null;
+  symbol?: string | null; // substring, case-insensitive
+  symbols?: string[]; // any of, exact
+  instrument?: string | null; // root symbol
+  session?: string | null;
+  sessions?: string[]; // any of
+  side?: 'long' | 'short' | null;
+  outcome?: 'win' | 'loss' | 'breakeven' | 'scratch' | null;
+  start_date?: string | null; // YYYY-MM-DD local, inclusive
+  end_date?: string | null; // YYYY-MM-DD local, inclusive
+  pnl_min?: number | null; // net, inclusive
+  pnl_max?: number | null;
+  rule_id?: string | null;
+  rule_checked?: boolean | null; // with rule_id; defaults to true
+  tags?: string[]; // tag ids, any of
+  notes?: string | null; // substring, case-insensitive
+};
+
+export type TradeSort = 'exit_time' | 'entry_time' | 'pnl_net' | 'symbol' | 'qty' | 'hold_duration';
+
+export type TradeQueryResult = {
+  trades: Trade[];
+  total: number; // all matches, ignoring limit/offset
+};
+
+export type TradeStats = {
+  trade_count: number;
+  wins: number;
+  losses: number;
+  breakeven: number;
+  win_rate_pct: number;
+  net_pnl: number;
+  gross_profit: number;
+  gross_loss: number; // positive
+  total_fees: number;
+  total_contracts: number;
+  avg_win: number;
+  avg_loss: number; // negative
+  profit_factor: number | null; // null without losing trades
+  expectancy: number; // per trade
+  expectancy_per_contract: number;
+  largest_win: number;
+  largest_loss: number; // negative
+  max_consecutive_wins: number;
+  max_consecutive_losses: number;
+  max_drawdown: number; // positive, on closed-trade equity
+  max_drawdown_peak_date: string | null; // null when the peak is the start
+  max_drawdown_trough_date: string | null;
+};
+
+export type EquityPoint = {
+  trade_id: string;
+  exit_time_utc: number;
+  date_local: string; // YYYY-MM-DD
+  pnl_net: number;
+  cumulative: number;
+  high_water_mark: number;
+  drawdown: number; // >= 0, below the high-water mark
+};
+
+export type DailyEquity = {
+  date_local: string; // YYYY-MM-DD
+  trade_count: number;
+  pnl_net: number;
+  cumulative: number;
+  high_water_mark: number;
+  drawdown: number;
+};
+
+export type EquityCurve = {
+  trades: EquityPoint[];
+  days: DailyEquity[];
+};
+
+export type BreakdownBy = 'time_of_day' | 'weekday' | 'session' | 'hold_duration';
+
+export type BreakdownBucket = {
+  key: string; // "09:30", "Fri", "ny", "5-15m"
+  trade_count: number;
+  wins: number;
+  net_pnl: number;
+  win_rate_pct: number;
+  expectancy: number;
+};
+
+export type AdherenceInterval = 'week' | 'month';
+
+export type AdherencePeriod = {
+  period: string; // "2024-W07" or "2024-02"
+  trade_count: number;
+  followed: number;
+  adherence_pct: number;
+};
+
+export type RuleAdherence = {
+  rule_id: string;
+  label: string;
+  adherence_pct: number;
+  followed: BreakdownBucket; // key "followed"
+  broken: BreakdownBucket; // key "broken"
+  cost_of_breaking: number | null; // broken trades x expectancy gap; null until both sides have trades
+  periods: AdherencePeriod[];
+};
+
+export type SearchHit = {
+  kind: 'trade' | 'journal';
+  id: string; // trade id or journal entry id
+  date_local: string; // YYYY-MM-DD
+  title: string; // trade symbol, or "Journal"
+  snippet: string; // matched terms wrapped in **
+  rank: number; // bm25, lower is better
+};
+
+export type TagPerformance = {
+  tag: Tag;
+  trade_count: number;
+  wins: number;
+  net_pnl: number;
+  win_rate_pct: number;
+  expectancy: number;
+  profit_factor: number | null; // null without losing trades
+};
+
+export type Attachment = {
+  id: string;
+  trade_id: string | null;
+  journal_entry_id: string | null;
+  file_name: string;
+  mime_type: 'image/png' | 'image/jpeg' | 'image/webp';
+  width: number;
+  height: number;
+  size_bytes: number;
+  thumbnail_base64: string; // PNG, at most 320px on the long side
+  created_at_utc: number;
+};
+
+export type AttachmentInput = {
+  // Exactly one of trade_id and date_local (journal day).
+  trade_id?: string | null;
+  date_local?: string | null;
+  file_name?: string;
+  data_base64: string;
+};
+
+export type AttachmentData = {
+  attachment: Attachment;
+  data_base64: string;
 };